@@ -1,3 +1,1197 @@
 /// Command line interface.
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Weekday};
+use clap::{App, Arg, SubCommand};
 
+use super::config::parse_utc_offset;
+use super::context::preset_names;
+use super::model::validate_profile;
+use super::task::ResetSchedule;
 
+fn validate_date(x: &str) -> Result<(), String> {
+    NaiveDate::parse_from_str(x, "%Y-%m-%d")
+        .map(|_| ())
+        .map_err(|_| String::from("The value must be a YYYY-MM-DD date"))
+}
+
+fn validate_time(x: &str) -> Result<(), String> {
+    NaiveTime::parse_from_str(x, "%H:%M")
+        .map(|_| ())
+        .map_err(|_| String::from("The value must be an HH:MM time"))
+}
+
+/// Parses a duration given in minutes ("40"), hours and minutes ("1h30m") or pomodoros ("3p"),
+/// where a pomodoro is `pomodoro` long.
+pub fn parse_duration(x: &str, pomodoro: Duration) -> Result<Duration, String> {
+    let invalid = || {
+        String::from("The value must be minutes (e.g. 40), hours and minutes (e.g. 1h30m) or pomodoros (e.g. 3p)")
+    };
+
+    if let Some(count) = x.strip_suffix('p') {
+        return count
+            .parse::<u32>()
+            .map(|count| pomodoro * count as i32)
+            .map_err(|_| invalid());
+    }
+    if let Ok(minutes) = x.parse::<u32>() {
+        return Ok(Duration::minutes(minutes as i64));
+    }
+    parse_hours_minutes(x).ok_or_else(invalid)
+}
+
+/// Parses hours and minutes like "1h30m", "1h 30m", "2h" or "45m".
+fn parse_hours_minutes(x: &str) -> Option<Duration> {
+    let (hours, minutes) = match x.split_once('h') {
+        Some((hours, minutes)) => (hours.trim().parse::<u32>().ok()?, minutes.trim()),
+        None => (0, x.trim()),
+    };
+    let minutes = match minutes.strip_suffix('m') {
+        Some(minutes) => minutes.trim().parse::<u32>().ok()?,
+        None if minutes.is_empty() && x.contains('h') => 0,
+        None => return None,
+    };
+    Some(Duration::minutes(hours as i64 * 60 + minutes as i64))
+}
+
+/// Parses a reset schedule: "daily", or a day of the week like "Mon" for a weekly reset.
+pub fn parse_reset(x: &str) -> Result<ResetSchedule, String> {
+    if x.eq_ignore_ascii_case("daily") {
+        return Ok(ResetSchedule::Daily);
+    }
+    x.parse::<Weekday>()
+        .map(ResetSchedule::Weekly)
+        .map_err(|_| String::from("The value must be daily or a day like Mon"))
+}
+
+/// Parses a comma separated list of days, where each item is a day ("Sat") or a range of days
+/// ("Mon-Fri"). Ranges wrap around the end of the week, so "Fri-Mon" is Friday through Monday. A
+/// range from a day to itself is ambiguous and rejected. Days listed twice are kept once.
+pub fn parse_days(x: &str) -> Result<Vec<Weekday>, String> {
+    let parse_day = |day: &str| {
+        day.trim().parse::<Weekday>().map_err(|_| {
+            format!(
+                "Unrecognized day '{}'. Use Sun, Mon, Tue, Wed, Thu, Fri, or Sat.",
+                day.trim()
+            )
+        })
+    };
+
+    let mut days = vec![];
+    for item in x
+        .split(',')
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty())
+    {
+        match item.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (parse_day(first)?, parse_day(last)?);
+                if first == last {
+                    return Err(format!(
+                        "The range '{}' starts and ends on the same day. Use '{}' for one day or list the whole week.",
+                        item, first
+                    ));
+                }
+                let mut day = first;
+                loop {
+                    if !days.contains(&day) {
+                        days.push(day);
+                    }
+                    if day == last {
+                        break;
+                    }
+                    day = day.succ();
+                }
+            }
+            None => {
+                let day = parse_day(item)?;
+                if !days.contains(&day) {
+                    days.push(day);
+                }
+            }
+        }
+    }
+    Ok(days)
+}
+
+fn validate_duration(x: &str) -> Result<(), String> {
+    parse_duration(x, Duration::minutes(25)).map(|_| ())
+}
+
+fn validate_timezone(x: &str) -> Result<(), String> {
+    parse_utc_offset(x).map(|_| ())
+}
+
+fn validate_rfc3339(x: &str) -> Result<(), String> {
+    DateTime::parse_from_rfc3339(x).map(|_| ()).map_err(|_| {
+        String::from("The value must be an RFC 3339 date and time, e.g. 2024-06-03T09:00:00Z")
+    })
+}
+
+fn validate_order(x: &str) -> Result<(), String> {
+    x.parse::<i32>()
+        .map(|_| ())
+        .map_err(|_| String::from("The value must be an integer"))
+}
+
+fn validate_positive(x: &str) -> Result<(), String> {
+    match x.parse::<i64>() {
+        Ok(value) if value > 0 => Ok(()),
+        _ => Err(String::from("The value must be a positive integer")),
+    }
+}
+
+/// Options that apply to every subcommand.
+fn build_global_args(app: App) -> App {
+    app.arg(
+        Arg::with_name("data-dir")
+            .long("data-dir")
+            .global(true)
+            .help("The directory holding the data file (also PREEMPT_DATA_DIR)")
+            .takes_value(true),
+    )
+    .arg(
+        Arg::with_name("profile")
+            .long("profile")
+            .global(true)
+            .help("Use a separate dataset, stored as preempt_<name>.yaml next to the default one")
+            .validator(validate_profile)
+            .takes_value(true),
+    )
+    .arg(
+        Arg::with_name("strict")
+            .long("strict")
+            .global(true)
+            .help("Fail without saving if anything would only be a warning, e.g. overlapping contexts or unscheduled tasks"),
+    )
+    .arg(
+        Arg::with_name("timezone")
+            .long("timezone")
+            .global(true)
+            .help("The UTC offset used to decide what \"today\" is, e.g. +02:00")
+            .validator(validate_timezone)
+            .allow_hyphen_values(true)
+            .takes_value(true),
+    )
+    .arg(
+        Arg::with_name("now")
+            .long("now")
+            .global(true)
+            .hide(true)
+            .help("Run as if it were this RFC 3339 instant, e.g. 2024-06-03T09:00:00Z")
+            .validator(validate_rfc3339)
+            .takes_value(true),
+    )
+}
+
+/// The basic task settings, shared by `add-task` and `plan`.
+fn task_args(command: App) -> App {
+    command
+        .arg(
+            Arg::with_name("duration")
+                .long("duration")
+                .help("The duration of the task in minutes, hours and minutes (1h30m), or pomodoros with a p suffix (3p)")
+                .takes_value(true)
+                .validator(validate_duration),
+        )
+        .arg(
+            Arg::with_name("context")
+                .long("context")
+                .help("The context of the task. Repeat it to list fallbacks, most preferred first")
+                .takes_value(true)
+                .multiple_occurrences(true),
+        )
+        .arg(
+            Arg::with_name("priority")
+                .long("priority")
+                .help("The priority of the task (0-10)")
+                .takes_value(true)
+                .validator(|x| match x.parse::<i32>() {
+                    Ok(priority) if (0..=10).contains(&priority) => Ok(()),
+                    _ => Err(String::from("The value must be an integer from 0 to 10")),
+                }),
+        )
+}
+
+fn build_add_task_arg(app: App) -> App {
+    let add_task = SubCommand::with_name("add-task")
+        .about("Adds a new task")
+        .arg(
+            Arg::with_name("name")
+                .long("name")
+                .required_unless_present("from-stdin")
+                .help("The name of the task")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("from-stdin")
+                .long("from-stdin")
+                .conflicts_with("name")
+                .help("Read task names from stdin, one per line, and add each with defaults"),
+        );
+    app.subcommand(
+        task_args(add_task)
+            .arg(
+                Arg::with_name("depends-on")
+                    .long("depends-on")
+                    .help("A task that must be finished first. May be given more than once")
+                    .takes_value(true)
+                    .multiple_occurrences(true),
+            )
+            .arg(
+                Arg::with_name("deadline")
+                    .long("deadline")
+                    .help("The date the task is due, as YYYY-MM-DD")
+                    .takes_value(true)
+                    .validator(validate_date),
+            )
+            .arg(
+                Arg::with_name("deadline-kind")
+                    .long("deadline-kind")
+                    .help("Whether missing the deadline is a warning (soft) or an error (hard)")
+                    .possible_values(["soft", "hard"])
+                    .requires("deadline")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("at")
+                    .long("at")
+                    .help("Pin the task to start at this time every day, as HH:MM")
+                    .takes_value(true)
+                    .validator(validate_time),
+            )
+            .arg(
+                Arg::with_name("min-block")
+                    .long("min-block")
+                    .help("Only schedule the task when at least this much time is free, in minutes or pomodoros (e.g. 2p)")
+                    .takes_value(true)
+                    .validator(validate_duration),
+            )
+            .arg(
+                Arg::with_name("uncertainty")
+                    .long("uncertainty")
+                    .help("How far the duration could be off either way, in minutes or pomodoros (e.g. 1p)")
+                    .takes_value(true)
+                    .validator(validate_duration),
+            )
+            .arg(
+                Arg::with_name("order")
+                    .long("order")
+                    .help("Schedule the task ahead of others in its priority class, lowest order first")
+                    .takes_value(true)
+                    .allow_hyphen_values(true)
+                    .validator(validate_order),
+            )
+            .arg(
+                Arg::with_name("difficulty")
+                    .long("difficulty")
+                    .help("How demanding the task is. High difficulty tasks go in high energy hours. Defaults to medium")
+                    .possible_values(["low", "medium", "high"])
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("reset")
+                    .long("reset")
+                    .help("Bring the task back undone and at full duration daily, or weekly on a day like Mon, finished or not")
+                    .takes_value(true)
+                    .validator(|x| parse_reset(x).map(|_| ())),
+            ),
+    )
+}
+
+fn build_plan_arg(app: App) -> App {
+    let plan = SubCommand::with_name("plan")
+        .about("Adds a task and shows today's timeline with it")
+        .arg(
+            Arg::with_name("name")
+                .long("name")
+                .required(true)
+                .help("The name of the task")
+                .takes_value(true),
+        );
+    app.subcommand(task_args(plan))
+}
+
+fn build_add_context_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("add-context")
+            .about("Adds a new context")
+            .arg(
+                Arg::with_name("name")
+                    .long("name")
+                    .required(true)
+                    .help("The name of the context")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("preset")
+                    .long("preset")
+                    .help("Take the days and hours from a common schedule instead")
+                    .possible_values(preset_names())
+                    .conflicts_with_all(&["days", "start", "end"])
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("days")
+                    .long("days")
+                    .required_unless_present("preset")
+                    .help("The days of the week for the context. Excepts Sun, Mon, Tue, Wed, Thu, Fri, Sat day codes, and ranges like Mon-Fri.")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("start")
+                    .long("start")
+                    .required_unless_present("preset")
+                    .help("The start time for the context")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("end")
+                    .long("end")
+                    .required_unless_present("preset")
+                    .help("The end time for the context")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("transition")
+                    .long("transition")
+                    .required(false)
+                    .help("The transition time between contexts, in minutes or hours and minutes (1h30m)")
+                    .takes_value(true)
+                    .validator(validate_duration),
+            )
+            .arg(
+                Arg::with_name("color")
+                    .long("color")
+                    .required(false)
+                    .help("The display color for the context, either a named color or #RRGGBB hex")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("from")
+                    .long("from")
+                    .required(false)
+                    .help("The first date the context is active, as YYYY-MM-DD")
+                    .takes_value(true)
+                    .validator(validate_date),
+            )
+            .arg(
+                Arg::with_name("until")
+                    .long("until")
+                    .required(false)
+                    .help("The last date the context is active, as YYYY-MM-DD")
+                    .takes_value(true)
+                    .validator(validate_date),
+            )
+            .arg(
+                Arg::with_name("focus")
+                    .long("focus")
+                    .help("Schedule each task as one long block with no breaks"),
+            )
+            .arg(
+                Arg::with_name("max-tasks")
+                    .long("max-tasks")
+                    .required(false)
+                    .help("The maximum number of distinct tasks to schedule in the context per day")
+                    .takes_value(true)
+                    .validator(|x| {
+                        x.parse::<usize>()
+                            .map(|_| ())
+                            .map_err(|_| String::from("The value must be a non-negative integer"))
+                    }),
+            )
+            .arg(
+                Arg::with_name("priority")
+                    .long("priority")
+                    .help("Which context wins time it shares with another; higher wins")
+                    .takes_value(true)
+                    .allow_hyphen_values(true)
+                    .validator(|x| {
+                        x.parse::<i32>()
+                            .map(|_| ())
+                            .map_err(|_| String::from("The value must be an integer"))
+                    }),
+            ),
+    )
+}
+
+fn build_remove_context_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("remove-context")
+            .about("Removes a context, leaving its tasks in place")
+            .arg(
+                Arg::with_name("name")
+                    .long("name")
+                    .required(true)
+                    .help("The name of the context")
+                    .takes_value(true),
+            ),
+    )
+}
+
+fn build_list_contexts_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("list-contexts")
+            .about("Lists every context")
+            .arg(
+                Arg::with_name("sort")
+                    .long("sort")
+                    .help("The order to list contexts in")
+                    .possible_values(["name", "tasks"])
+                    .takes_value(true),
+            ),
+    )
+}
+
+fn build_show_context_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("show-context")
+            .about("Shows details about a specific context")
+            .arg(
+                Arg::with_name("name")
+                    .required(true)
+                    .help("The name of the context"),
+            )
+            .arg(
+                Arg::with_name("date")
+                    .long("date")
+                    .help("Also show the context's effective window on this date, as YYYY-MM-DD")
+                    .takes_value(true)
+                    .validator(validate_date),
+            ),
+    )
+}
+
+fn build_add_exception_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("add-exception")
+            .about("Overrides, cancels, or extends a context's hours on a specific date")
+            .arg(
+                Arg::with_name("context")
+                    .long("context")
+                    .required(true)
+                    .help("The name of the context")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("date")
+                    .long("date")
+                    .required(true)
+                    .help("The date of the exception, as YYYY-MM-DD")
+                    .takes_value(true)
+                    .validator(validate_date),
+            )
+            .arg(
+                Arg::with_name("start")
+                    .long("start")
+                    .required_unless_present("mode")
+                    .required_if_eq_any(&[("mode", "override"), ("mode", "extend")])
+                    .help("The start time on that date")
+                    .takes_value(true)
+                    .validator(validate_time),
+            )
+            .arg(
+                Arg::with_name("end")
+                    .long("end")
+                    .required_unless_present("mode")
+                    .required_if_eq_any(&[("mode", "override"), ("mode", "extend")])
+                    .help("The end time on that date")
+                    .takes_value(true)
+                    .validator(validate_time),
+            )
+            .arg(
+                Arg::with_name("mode")
+                    .long("mode")
+                    .help("Replace the day's hours, cancel the day, or add these hours to it")
+                    .possible_values(["override", "cancel", "extend"])
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("transition")
+                    .long("transition")
+                    .required(false)
+                    .help(
+                        "The transition time on that date, in minutes or hours and minutes (1h30m)",
+                    )
+                    .takes_value(true)
+                    .validator(validate_duration),
+            ),
+    )
+}
+
+/// Flags that tune how a schedule is built, shared by `timeline` and `save-preset`.
+fn schedule_option_args(command: App) -> App {
+    command
+        .arg(
+            Arg::with_name("shuffle-low")
+                .long("shuffle-low")
+                .help("Shuffle low priority tasks into a different order each day"),
+        )
+        .arg(
+            Arg::with_name("strategy")
+                .long("strategy")
+                .help("The scheduling strategy to use")
+                .possible_values(["multilevel", "weighted"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("priority-boost")
+                .long("priority-boost")
+                .help("Temporarily raise the priority of tasks at least this many days old")
+                .takes_value(true)
+                .validator(|x| {
+                    x.parse::<u32>()
+                        .map(|_| ())
+                        .map_err(|_| String::from("The value must be a non-negative integer"))
+                }),
+        )
+        .arg(
+            Arg::with_name("weighted-low")
+                .long("weighted-low")
+                .help("Give higher priority low tasks proportionally more turns"),
+        )
+        .arg(
+            Arg::with_name("skip-transition-break")
+                .long("skip-transition-break")
+                .help("Start low priority work right after the last high or medium block, without a short break"),
+        )
+        .arg(
+            Arg::with_name("breaks-as")
+                .long("breaks-as")
+                .help("Show breaks as their own blocks, or leave them as free gaps")
+                .possible_values(["explicit", "free"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("align")
+                .long("align")
+                .help("Round block start times up to a multiple of this many minutes")
+                .takes_value(true)
+                .validator(|x| match x.parse::<u32>() {
+                    Ok(minutes) if minutes > 0 => Ok(()),
+                    _ => Err(String::from("The value must be a positive integer")),
+                }),
+        )
+        .arg(
+            Arg::with_name("max-consecutive")
+                .long("max-consecutive")
+                .help("Let a task have at most this many work blocks in a row")
+                .takes_value(true)
+                .validator(validate_positive),
+        )
+}
+
+fn build_save_preset_arg(app: App) -> App {
+    app.subcommand(schedule_option_args(
+        SubCommand::with_name("save-preset")
+            .about("Saves scheduling settings under a name for use with timeline --preset")
+            .arg(
+                Arg::with_name("name")
+                    .required(true)
+                    .help("The name of the preset")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("work-minutes")
+                    .long("work-minutes")
+                    .help("The length of a work block")
+                    .takes_value(true)
+                    .validator(validate_positive),
+            )
+            .arg(
+                Arg::with_name("short-break-minutes")
+                    .long("short-break-minutes")
+                    .help("The length of a short break")
+                    .takes_value(true)
+                    .validator(validate_positive),
+            )
+            .arg(
+                Arg::with_name("long-break-minutes")
+                    .long("long-break-minutes")
+                    .help("The length of a long break")
+                    .takes_value(true)
+                    .validator(validate_positive),
+            )
+            .arg(
+                Arg::with_name("long-break-every")
+                    .long("long-break-every")
+                    .help("Take a long break after this many work blocks")
+                    .takes_value(true)
+                    .validator(validate_positive),
+            ),
+    ))
+}
+
+fn build_timeline_arg(app: App) -> App {
+    let timeline = schedule_option_args(
+        SubCommand::with_name("timeline")
+            .about("Creates and shows a timeline incorporating the current tasks.")
+            .arg(
+                Arg::with_name("context")
+                    .long("context")
+                    .help("Only schedule this context. May be given more than once")
+                    .takes_value(true)
+                    .multiple_occurrences(true),
+            )
+            .arg(
+                Arg::with_name("show-free")
+                    .long("show-free")
+                    .help("Show active contexts that have no tasks as free time"),
+            )
+            .arg(
+                Arg::with_name("preset")
+                    .long("preset")
+                    .help("Start from a preset saved with save-preset; other flags apply on top")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("limit")
+                    .long("limit")
+                    .help("Only show the first N blocks of the timeline")
+                    .takes_value(true)
+                    .validator(|x| {
+                        x.parse::<usize>()
+                            .map(|_| ())
+                            .map_err(|_| String::from("The value must be a non-negative integer"))
+                    }),
+            )
+            .arg(
+                Arg::with_name("date")
+                    .long("date")
+                    .conflicts_with("week")
+                    .help("Plan this date (YYYY-MM-DD) instead of today")
+                    .takes_value(true)
+                    .validator(validate_date),
+            )
+            .arg(
+                Arg::with_name("week")
+                    .long("week")
+                    .help("Show the whole week, one section per day"),
+            )
+            .arg(
+                Arg::with_name("until-done")
+                    .long("until-done")
+                    .conflicts_with("week")
+                    .help("Plan day after day, carrying unfinished work forward, until every task has its time"),
+            )
+            .arg(
+                Arg::with_name("max-days")
+                    .long("max-days")
+                    .requires("until-done")
+                    .default_value("30")
+                    .help("The most days --until-done plans before reporting what's left")
+                    .takes_value(true)
+                    .validator(|x| match x.parse::<usize>() {
+                        Ok(days) if days > 0 => Ok(()),
+                        _ => Err(String::from("The value must be a positive integer")),
+                    }),
+            )
+            .arg(
+                Arg::with_name("week-start")
+                    .long("week-start")
+                    .requires("week")
+                    .help("The day the week starts on (Sun, Mon, ...). Defaults to the stored setting")
+                    .takes_value(true)
+                    .validator(|x| {
+                        x.parse::<Weekday>()
+                            .map(|_| ())
+                            .map_err(|_| String::from("The value must be a day like Sun or Mon"))
+                    }),
+            )
+            .arg(
+                Arg::with_name("week-numbers")
+                    .long("week-numbers")
+                    .requires("week")
+                    .help("Prefix each day header with its ISO week number, like W23"),
+            )
+            .arg(
+                Arg::with_name("coalesce")
+                    .long("coalesce")
+                    .help("Merge back-to-back blocks of the same task into one block"),
+            )
+            .arg(
+                Arg::with_name("save")
+                    .long("save")
+                    .help("Keep the generated schedule so show-schedule can recall it later"),
+            )
+            .arg(
+                Arg::with_name("output-file")
+                    .long("output-file")
+                    .help("Write the timeline to this file instead of stdout")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("compact")
+                    .long("compact")
+                    .conflicts_with_all(&["week", "until-done"])
+                    .help("Print one start|end|name line per block, for scripts and status bars"),
+            )
+            .arg(
+                Arg::with_name("explain-unscheduled")
+                    .long("explain-unscheduled")
+                    .conflicts_with("compact")
+                    .help("List every open task with whether it was scheduled, and if not, why"),
+            ),
+    );
+    app.subcommand(timeline)
+}
+
+fn build_tui_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("tui")
+            .about("Shows today's timeline as a view that scrolls with the arrow keys; q quits")
+            .arg(
+                Arg::with_name("height")
+                    .long("height")
+                    .default_value("10")
+                    .help("How many blocks to print when output isn't a terminal")
+                    .takes_value(true)
+                    .validator(validate_positive),
+            ),
+    )
+}
+
+fn build_next_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("next").about("Shows the block in progress and the one after it"),
+    )
+}
+
+fn build_stats_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("stats")
+            .about("Shows how much work is scheduled today")
+            .arg(
+                Arg::with_name("week")
+                    .long("week")
+                    .help("Show each day of the week and which one is busiest"),
+            )
+            .arg(
+                Arg::with_name("schedule")
+                    .long("schedule")
+                    .conflicts_with("week")
+                    .help("Show today's work and break time, and breaks as a share of work"),
+            ),
+    )
+}
+
+fn build_show_schedule_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("show-schedule")
+            .about("Shows a schedule kept with timeline --save")
+            .arg(
+                Arg::with_name("date")
+                    .long("date")
+                    .help("The date of the schedule (YYYY-MM-DD), today by default")
+                    .takes_value(true)
+                    .validator(validate_date),
+            ),
+    )
+}
+
+fn build_lock_schedule_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("lock-schedule")
+            .about(
+                "Saves the schedule for a date and keeps timeline showing it until it's unlocked",
+            )
+            .arg(
+                Arg::with_name("date")
+                    .long("date")
+                    .help("The date of the schedule (YYYY-MM-DD), today by default")
+                    .takes_value(true)
+                    .validator(validate_date),
+            ),
+    )
+}
+
+fn build_unlock_schedule_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("unlock-schedule")
+            .about("Lets timeline build the schedule for a locked date again")
+            .arg(
+                Arg::with_name("date")
+                    .long("date")
+                    .help("The date of the schedule (YYYY-MM-DD), today by default")
+                    .takes_value(true)
+                    .validator(validate_date),
+            ),
+    )
+}
+
+fn build_diff_schedule_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("diff-schedule")
+            .about("Compares a saved schedule with one built from the current tasks")
+            .arg(
+                Arg::with_name("date")
+                    .long("date")
+                    .help("The date of the saved schedule (YYYY-MM-DD), today by default")
+                    .takes_value(true)
+                    .validator(validate_date),
+            ),
+    )
+}
+
+fn build_export_md_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("export-md")
+            .about("Exports today's schedule as a Markdown checklist")
+            .arg(
+                Arg::with_name("output")
+                    .long("output")
+                    .alias("output-file")
+                    .required(true)
+                    .help("The path of the Markdown file to write")
+                    .takes_value(true),
+            ),
+    )
+}
+
+fn build_today_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("today")
+            .about("Lists tasks whose context is active today and tasks due today"),
+    )
+}
+
+fn build_list_tasks_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("list-tasks")
+            .about("Lists all tasks")
+            .arg(
+                Arg::with_name("include-archived")
+                    .long("include-archived")
+                    .help("Also list archived tasks"),
+            )
+            .arg(
+                Arg::with_name("explain-priority")
+                    .long("explain-priority")
+                    .help("Show the priority class each task is scheduled in"),
+            )
+            .arg(
+                Arg::with_name("since")
+                    .long("since")
+                    .help("Only list tasks created on or after this date, as YYYY-MM-DD")
+                    .takes_value(true)
+                    .validator(validate_date),
+            )
+            .arg(
+                Arg::with_name("snoozed")
+                    .long("snoozed")
+                    .help("Only list tasks that are snoozed today"),
+            )
+            .arg(
+                Arg::with_name("sort")
+                    .long("sort")
+                    .help("The order to list tasks in")
+                    .possible_values(["created"])
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("units")
+                    .long("units")
+                    .help("How to show durations")
+                    .possible_values(["minutes", "pomodoros"])
+                    .takes_value(true),
+            ),
+    )
+}
+
+fn build_show_task_arg(app: App) -> App {
+    app.subcommand(task_context_arg(
+        SubCommand::with_name("show-task")
+            .about("Shows details about a specific task")
+            .arg(
+                Arg::with_name("name")
+                    .required(true)
+                    .help("The name of the task"),
+            )
+            .arg(
+                Arg::with_name("units")
+                    .long("units")
+                    .help("How to show durations")
+                    .possible_values(["minutes", "pomodoros"])
+                    .takes_value(true),
+            ),
+    ))
+}
+
+/// Picks between tasks that share a name under per-context uniqueness.
+fn task_context_arg(command: App) -> App {
+    command.arg(
+        Arg::with_name("context")
+            .long("context")
+            .help("The context the task is listed under first, for tasks that share a name")
+            .takes_value(true),
+    )
+}
+
+fn build_archive_task_arg(app: App) -> App {
+    app.subcommand(task_context_arg(
+        SubCommand::with_name("archive-task")
+            .about("Archives a task, keeping it for history without scheduling it")
+            .arg(
+                Arg::with_name("name")
+                    .long("name")
+                    .required(true)
+                    .help("The name of the task")
+                    .takes_value(true),
+            ),
+    ))
+}
+
+fn build_remove_task_arg(app: App) -> App {
+    app.subcommand(task_context_arg(
+        SubCommand::with_name("remove-task")
+            .about("Removes a task for good, without keeping it for history")
+            .arg(
+                Arg::with_name("name")
+                    .long("name")
+                    .required(true)
+                    .help("The name of the task")
+                    .takes_value(true),
+            ),
+    ))
+}
+
+fn build_snooze_task_arg(app: App) -> App {
+    app.subcommand(task_context_arg(
+        SubCommand::with_name("snooze-task")
+            .about("Keeps a task out of schedules until a later date")
+            .arg(
+                Arg::with_name("name")
+                    .long("name")
+                    .required(true)
+                    .help("The name of the task")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("until")
+                    .long("until")
+                    .required(true)
+                    .help("The date the task comes back, as YYYY-MM-DD")
+                    .takes_value(true)
+                    .validator(validate_date),
+            ),
+    ))
+}
+
+fn build_reorder_task_arg(app: App) -> App {
+    app.subcommand(task_context_arg(
+        SubCommand::with_name("reorder-task")
+            .about("Sets a task's place within its priority class")
+            .arg(
+                Arg::with_name("name")
+                    .long("name")
+                    .required(true)
+                    .help("The name of the task")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("order")
+                    .long("order")
+                    .required_unless_present("clear")
+                    .help("The task's order. Lower orders are scheduled first")
+                    .takes_value(true)
+                    .allow_hyphen_values(true)
+                    .validator(validate_order),
+            )
+            .arg(
+                Arg::with_name("clear")
+                    .long("clear")
+                    .conflicts_with("order")
+                    .help("Remove the order, going back to the usual placement"),
+            ),
+    ))
+}
+
+fn build_deps_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("deps")
+            .about("Shows a task's prerequisites as a tree")
+            .arg(
+                Arg::with_name("task")
+                    .long("task")
+                    .required(true)
+                    .help("The name of the task")
+                    .takes_value(true),
+            ),
+    )
+}
+
+fn build_split_task_arg(app: App) -> App {
+    app.subcommand(task_context_arg(
+        SubCommand::with_name("split-task")
+            .about("Splits a task into smaller, equally sized tasks")
+            .arg(
+                Arg::with_name("name")
+                    .long("name")
+                    .required(true)
+                    .help("The name of the task")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("into")
+                    .long("into")
+                    .required(true)
+                    .help("The number of pieces to split the task into")
+                    .takes_value(true)
+                    .validator(|x| match x.parse::<usize>() {
+                        Ok(n) if n >= 2 => Ok(()),
+                        _ => Err(String::from("The value must be an integer of at least 2")),
+                    }),
+            ),
+    ))
+}
+
+fn build_roll_over_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("roll-over")
+            .about("Applies a day's planned work to tasks and carries the rest forward")
+            .arg(
+                Arg::with_name("from")
+                    .long("from")
+                    .help("The day whose plan was worked, as YYYY-MM-DD. Defaults to yesterday")
+                    .takes_value(true)
+                    .validator(validate_date),
+            )
+            .arg(
+                Arg::with_name("to")
+                    .long("to")
+                    .help("The day to carry remaining work to, as YYYY-MM-DD. Defaults to today")
+                    .takes_value(true)
+                    .validator(validate_date),
+            ),
+    )
+}
+
+fn build_import_csv_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("import-csv")
+            .about("Imports tasks from a CSV file with a name,duration,priority,context,description header")
+            .arg(
+                Arg::with_name("file")
+                    .long("file")
+                    .required(true)
+                    .help("The path of the CSV file")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("dedupe")
+                    .long("dedupe")
+                    .help("Merge rows whose task already exists into it, adding up their durations and contexts"),
+            ),
+    )
+}
+
+fn build_import_holidays_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("import-holidays")
+            .about("Cancels a context on each date in a file of YYYY-MM-DD lines")
+            .arg(
+                Arg::with_name("context")
+                    .long("context")
+                    .required(true)
+                    .help("The context to cancel")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("file")
+                    .long("file")
+                    .required(true)
+                    .help("The path of the file of dates")
+                    .takes_value(true),
+            ),
+    )
+}
+
+fn build_purge_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("purge")
+            .about("Backs up the data file, then deletes all tasks, contexts and settings")
+            .arg(
+                Arg::with_name("confirm")
+                    .long("confirm")
+                    .help("Actually purge; without it nothing happens"),
+            ),
+    )
+}
+
+fn build_check_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("check")
+            .alias("doctor")
+            .about("Checks the data file for problems without changing it"),
+    )
+}
+
+fn build_version_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("version").about("Shows the version and where data is stored"),
+    )
+}
+
+fn build_schema_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("schema").about("Prints a JSON Schema describing the data file"),
+    )
+}
+
+fn build_completion_arg(app: App) -> App {
+    app.subcommand(
+        SubCommand::with_name("completion")
+            .about("Generates a shell completion script")
+            .hide(true)
+            .arg(
+                Arg::with_name("shell")
+                    .long("shell")
+                    .required(true)
+                    .help("The shell to generate completions for")
+                    .possible_values(["bash", "zsh", "fish"])
+                    .takes_value(true),
+            ),
+    )
+}
+
+/// Builds the full command line definition.
+///
+/// Kept separate from `main` so completions and argument parsing can reuse it.
+pub fn build_cli() -> App<'static> {
+    let app = App::new("preempt")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("A scheduler for humans.");
+    let app = build_global_args(app);
+    let app = build_add_task_arg(app);
+    let app = build_plan_arg(app);
+    let app = build_add_context_arg(app);
+    let app = build_remove_context_arg(app);
+    let app = build_list_contexts_arg(app);
+    let app = build_show_context_arg(app);
+    let app = build_add_exception_arg(app);
+    let app = build_save_preset_arg(app);
+    let app = build_timeline_arg(app);
+    let app = build_tui_arg(app);
+    let app = build_next_arg(app);
+    let app = build_stats_arg(app);
+    let app = build_show_schedule_arg(app);
+    let app = build_lock_schedule_arg(app);
+    let app = build_unlock_schedule_arg(app);
+    let app = build_diff_schedule_arg(app);
+    let app = build_export_md_arg(app);
+    let app = build_today_arg(app);
+    let app = build_list_tasks_arg(app);
+    let app = build_show_task_arg(app);
+    let app = build_archive_task_arg(app);
+    let app = build_remove_task_arg(app);
+    let app = build_snooze_task_arg(app);
+    let app = build_reorder_task_arg(app);
+    let app = build_deps_arg(app);
+    let app = build_split_task_arg(app);
+    let app = build_roll_over_arg(app);
+    let app = build_import_csv_arg(app);
+    let app = build_import_holidays_arg(app);
+    let app = build_purge_arg(app);
+    let app = build_check_arg(app);
+    let app = build_version_arg(app);
+    let app = build_schema_arg(app);
+    build_completion_arg(app)
+}
@@ -0,0 +1,36 @@
+use std::sync::RwLock;
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, Utc};
+
+/// The current time, as seen by the rest of the app.
+///
+/// Times are kept as naive wall-clock values. By default that wall clock is UTC; the configured
+/// timezone shifts it so "today" matches the user's day rather than Greenwich's.
+static UTC_OFFSET: RwLock<Option<FixedOffset>> = RwLock::new(None);
+
+/// A fixed instant to use instead of the system clock, for reproducible runs.
+static FROZEN_NOW: RwLock<Option<DateTime<Utc>>> = RwLock::new(None);
+
+/// Freezes the clock at the given instant for the rest of the run. The UTC offset still applies.
+pub fn set_now(instant: DateTime<Utc>) {
+    *FROZEN_NOW.write().unwrap() = Some(instant);
+}
+
+/// Sets the offset applied to the wall clock for the rest of the run.
+pub fn set_utc_offset(offset: FixedOffset) {
+    *UTC_OFFSET.write().unwrap() = Some(offset);
+}
+
+/// The current wall-clock time.
+pub fn now() -> NaiveDateTime {
+    let now = FROZEN_NOW.read().unwrap().unwrap_or_else(Utc::now);
+    match *UTC_OFFSET.read().unwrap() {
+        Some(offset) => now.with_timezone(&offset).naive_local(),
+        None => now.naive_utc(),
+    }
+}
+
+/// The current wall-clock date.
+pub fn today() -> NaiveDate {
+    now().date()
+}
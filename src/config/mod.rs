@@ -0,0 +1,88 @@
+use std::path::{Path, PathBuf};
+
+use chrono::FixedOffset;
+use serde::Deserialize;
+
+/// User settings read from `config.toml`.
+use super::model::{get_dir, UniqueScope};
+use super::schedule::PomodoroConfig;
+
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// Settings that live outside the data file. Everything is optional, and command line flags win
+/// over anything set here.
+#[derive(Deserialize, Default, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Where the data file is kept, instead of the platform data directory.
+    pub data_dir: Option<PathBuf>,
+    /// A fixed UTC offset such as "+02:00", or "UTC".
+    pub timezone: Option<String>,
+    pub pomodoro: Option<PomodoroConfig>,
+    /// Overrides `unique_scope` in the data file.
+    pub unique_scope: Option<UniqueScope>,
+}
+
+/// The path of the config file, if a config directory could be found.
+pub fn get_config_path() -> Option<PathBuf> {
+    get_dir().map(|proj_dirs| proj_dirs.config_dir().join(CONFIG_FILE_NAME))
+}
+
+/// Parses a timezone given as "UTC" or a fixed offset like "+02:00" or "-0530".
+pub fn parse_utc_offset(timezone: &str) -> Result<FixedOffset, String> {
+    let invalid = || {
+        format!(
+            "Invalid timezone '{}', expected UTC or an offset like +02:00",
+            timezone
+        )
+    };
+
+    if timezone.eq_ignore_ascii_case("utc") || timezone == "Z" {
+        return Ok(FixedOffset::east_opt(0).unwrap());
+    }
+
+    let sign = match timezone.chars().next() {
+        Some('+') => 1,
+        Some('-') => -1,
+        _ => return Err(invalid()),
+    };
+    let digits = timezone[1..].replace(':', "");
+    if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    // safe to unwrap because both halves are checked to be digits
+    let hours: i32 = digits[..2].parse().unwrap();
+    let minutes: i32 = digits[2..].parse().unwrap();
+    if minutes >= 60 {
+        return Err(invalid());
+    }
+
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).ok_or_else(invalid)
+}
+
+impl Config {
+    /// Loads the config file from the config directory. A missing file gives the defaults.
+    pub fn load() -> Result<Config, String> {
+        match get_config_path() {
+            Some(path) if path.exists() => Config::load_from(&path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Config, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+        let config: Config = toml::from_str(&contents)
+            .map_err(|e| format!("Invalid config {}: {}", path.display(), e))?;
+
+        if let Some(timezone) = &config.timezone {
+            parse_utc_offset(timezone)?;
+        }
+        if let Some(pomodoro) = &config.pomodoro {
+            pomodoro
+                .validate()
+                .map_err(|e| format!("Invalid config {}: {}", path.display(), e))?;
+        }
+        Ok(config)
+    }
+}
@@ -1,20 +1,78 @@
 use std::cmp::Ordering;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
 
 /// Utilities for manipulating context.
-use chrono::{Datelike, Duration, NaiveDate, NaiveTime, Weekday};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Weekday};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use serde_with::{serde_as, DurationSeconds};
+use serde_with::serde_as;
 
+use super::readable::{ClockTime, Minutes};
+use super::task::{format_duration, normalize_name};
 use super::timeblock::TimeBlock;
 
+type Preset = (&'static str, &'static [Weekday], (u32, u32), (u32, u32));
+
+const WEEKDAYS: &[Weekday] = &[
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+];
+const WEEKEND: &[Weekday] = &[Weekday::Sat, Weekday::Sun];
+
+/// Common schedules for `Context::from_preset`: a name, the days, and the start and end as
+/// (hour, minute).
+const PRESETS: &[Preset] = &[
+    ("nine_to_five", WEEKDAYS, (9, 0), (17, 0)),
+    ("weekday_evenings", WEEKDAYS, (18, 0), (21, 0)),
+    ("weekend_mornings", WEEKEND, (8, 0), (12, 0)),
+    ("weekend_afternoons", WEEKEND, (13, 0), (17, 0)),
+];
+
+/// Whether `Context::colorize` adds color. Off unless turned on, so text bound for a file or a
+/// pipe stays plain.
+static COLORS: AtomicBool = AtomicBool::new(false);
+
+/// Turns context colors on or off for the rest of the run.
+pub fn set_colors(enabled: bool) {
+    COLORS.store(enabled, AtomicOrdering::Relaxed);
+}
+
+/// The names `Context::from_preset` accepts.
+pub fn preset_names() -> Vec<&'static str> {
+    PRESETS.iter().map(|(name, ..)| *name).collect()
+}
+
+/// What an exception does to the context's regular window on its date.
+#[derive(Serialize, Deserialize, JsonSchema, Default, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExceptionMode {
+    /// The exception's hours replace the regular ones.
+    #[default]
+    Override,
+    /// The context is off for the day.
+    Cancel,
+    /// The exception's hours are added to the regular ones.
+    Extend,
+}
+
 #[serde_as]
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
 pub struct ContextException {
     date: NaiveDate,
+    #[serde_as(as = "ClockTime")]
+    #[schemars(with = "ClockTime")]
     start_time: NaiveTime,
+    #[serde_as(as = "ClockTime")]
+    #[schemars(with = "ClockTime")]
     end_time: NaiveTime,
-    #[serde_as(as = "DurationSeconds<i64>")]
+    #[serde_as(as = "Minutes")]
+    #[schemars(with = "Minutes")]
     transition_time: Duration,
+    #[serde(default)]
+    mode: ExceptionMode,
 }
 
 impl ContextException {
@@ -29,8 +87,121 @@ impl ContextException {
             start_time,
             end_time,
             transition_time,
+            mode: ExceptionMode::Override,
+        }
+    }
+
+    /// An exception that adds hours to the day's regular window, e.g. for a crunch day.
+    pub fn extension(
+        date: NaiveDate,
+        start_time: NaiveTime,
+        end_time: NaiveTime,
+        transition_time: Duration,
+    ) -> Self {
+        ContextException {
+            mode: ExceptionMode::Extend,
+            ..ContextException::new(date, start_time, end_time, transition_time)
+        }
+    }
+
+    /// An exception that turns the context off for the whole day, e.g. for a holiday.
+    pub fn cancellation(date: NaiveDate) -> Self {
+        ContextException {
+            date,
+            start_time: NaiveTime::MIN,
+            end_time: NaiveTime::MIN,
+            transition_time: Duration::zero(),
+            mode: ExceptionMode::Cancel,
+        }
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        self.date
+    }
+
+    pub fn mode(&self) -> ExceptionMode {
+        self.mode
+    }
+}
+
+/// The days sorted Monday first, each listed once.
+fn sorted_days(mut days: Vec<Weekday>) -> Vec<Weekday> {
+    days.sort_unstable_by(|a, b| {
+        if a.number_from_monday() - 1 > b.number_from_monday() - 1 {
+            Ordering::Greater
+        } else if a.number_from_monday() - 1 < b.number_from_monday() - 1 {
+            Ordering::Less
+        } else {
+            Ordering::Equal
+        }
+    });
+    days.dedup();
+
+    days
+}
+
+/// How long a window is. Windows that run past midnight are counted in full.
+fn window_length(block: &TimeBlock) -> Duration {
+    let length = block.duration();
+    if length < Duration::zero() {
+        length + Duration::days(1)
+    } else {
+        length
+    }
+}
+
+/// Named colors accepted for contexts, along with their ANSI foreground codes.
+const NAMED_COLORS: [(&str, u8); 8] = [
+    ("black", 30),
+    ("red", 31),
+    ("green", 32),
+    ("yellow", 33),
+    ("blue", 34),
+    ("magenta", 35),
+    ("cyan", 36),
+    ("white", 37),
+];
+
+/// Converts a named color or `#RRGGBB` hex color into an ANSI escape sequence.
+fn ansi_escape(color: &str) -> Option<String> {
+    if let Some((_, code)) = NAMED_COLORS
+        .iter()
+        .find(|(name, _)| *name == color.to_lowercase())
+    {
+        return Some(format!("\x1b[{}m", code));
+    }
+
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(format!(
+        "\x1b[38;2;{};{};{}m",
+        channel(0)?,
+        channel(2)?,
+        channel(4)?
+    ))
+}
+
+/// Sorts same-day windows by start and merges any that overlap or touch. Windows running past
+/// midnight are kept as they are.
+fn merge_windows(mut windows: Vec<TimeBlock>) -> Vec<TimeBlock> {
+    windows.sort_by_key(|window| window.start_time);
+    let mut merged: Vec<TimeBlock> = vec![];
+    for window in windows {
+        match merged.last_mut() {
+            Some(last)
+                if last.start_time < last.end_time
+                    && window.start_time < window.end_time
+                    && window.start_time <= last.end_time =>
+            {
+                last.end_time = last.end_time.max(window.end_time);
+            }
+            _ => merged.push(window),
         }
     }
+    merged
 }
 
 /// A description of a context. A context is described sort-of like a recurring calendar invite.
@@ -38,15 +209,34 @@ impl ContextException {
 /// Note that contexts do not have a timezone. Timezones are applied right before outputting
 /// results and are not included on this level to keep things simple. All times and dates are assumed to be UTC.
 #[serde_as]
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
 pub struct Context {
     pub name: String,
     pub days: Vec<Weekday>,
+    #[serde_as(as = "ClockTime")]
+    #[schemars(with = "ClockTime")]
     pub start: NaiveTime,
+    #[serde_as(as = "ClockTime")]
+    #[schemars(with = "ClockTime")]
     pub end: NaiveTime,
-    #[serde_as(as = "DurationSeconds<i64>")]
+    #[serde_as(as = "Minutes")]
+    #[schemars(with = "Minutes")]
     pub transition: Duration,
     exceptions: Vec<ContextException>,
+    /// The most distinct tasks that will be started in this context on a single day.
+    pub max_tasks_per_day: Option<usize>,
+    /// A named color or `#RRGGBB` hex color used when displaying the context.
+    color: Option<String>,
+    /// The first date the context is active. Unbounded when unset.
+    pub active_from: Option<NaiveDate>,
+    /// The last date the context is active, inclusive. Unbounded when unset.
+    pub active_until: Option<NaiveDate>,
+    /// Focus contexts schedule each task as one long block with no breaks.
+    #[serde(default)]
+    pub focus_mode: bool,
+    /// Where this context overlaps another on a day, the higher priority one gets the shared time.
+    #[serde(default)]
+    pub priority: i32,
 }
 
 impl Context {
@@ -58,75 +248,343 @@ impl Context {
         transition: Duration,
     ) -> Self {
         Self {
-            name: name.to_string(),
-            days,
+            name: normalize_name(name),
+            days: sorted_days(days),
             start,
             end,
             transition,
             exceptions: vec![],
+            max_tasks_per_day: None,
+            color: None,
+            active_from: None,
+            active_until: None,
+            focus_mode: false,
+            priority: 0,
         }
     }
 
-    fn get_days(&self) -> Vec<Weekday> {
-        let mut days = self.days.clone();
+    /// Like `new`, but rejects a context without any days, which could never become active.
+    pub fn try_new(
+        name: &str,
+        days: Vec<Weekday>,
+        start: NaiveTime,
+        end: NaiveTime,
+        transition: Duration,
+    ) -> Result<Self, &'static str> {
+        if days.is_empty() {
+            return Err("A context needs at least one day");
+        }
+        Ok(Self::new(name, days, start, end, transition))
+    }
 
-        days.sort_unstable_by(|a, b| {
-            if a.number_from_monday() - 1 > b.number_from_monday() - 1 {
-                return Ordering::Greater;
-            } else if a.number_from_monday() - 1 < b.number_from_monday() - 1 {
-                return Ordering::Less;
-            } else {
-                return Ordering::Equal;
+    /// Builds a context from one of the named `PRESETS`, such as "nine_to_five" for Mon-Fri
+    /// 09:00 to 17:00.
+    pub fn from_preset(preset: &str, context_name: &str) -> Result<Self, String> {
+        let (_, days, (start_hour, start_minute), (end_hour, end_minute)) = PRESETS
+            .iter()
+            .find(|(name, ..)| *name == preset)
+            .ok_or_else(|| {
+                format!(
+                    "Unknown preset '{}'. Choose one of: {}",
+                    preset,
+                    preset_names().join(", ")
+                )
+            })?;
+
+        // safe to unwrap because the preset times are valid
+        let start = NaiveTime::from_hms_opt(*start_hour, *start_minute, 0).unwrap();
+        let end = NaiveTime::from_hms_opt(*end_hour, *end_minute, 0).unwrap();
+        Ok(Self::new(
+            context_name,
+            days.to_vec(),
+            start,
+            end,
+            Duration::zero(),
+        ))
+    }
+
+    /// Sets the display color. Accepts a named color (e.g. "blue") or a `#RRGGBB` hex color.
+    pub fn set_color(&mut self, color: &str) -> Result<(), &'static str> {
+        if ansi_escape(color).is_none() {
+            return Err("Invalid color. Use a named color (black, red, green, yellow, blue, magenta, cyan, white) or a #RRGGBB hex color.");
+        }
+        self.color = Some(color.to_string());
+        Ok(())
+    }
+
+    pub fn color(&self) -> Option<&str> {
+        self.color.as_deref()
+    }
+
+    /// Wraps the text in the context's color, if it has one and colors are on.
+    pub fn colorize(&self, text: &str) -> String {
+        match self.color.as_deref().and_then(ansi_escape) {
+            Some(escape) if COLORS.load(AtomicOrdering::Relaxed) => {
+                format!("{}{}\x1b[0m", escape, text)
             }
+            _ => text.to_string(),
+        }
+    }
+
+    /// Replaces the context's days, dropping repeats and sorting them Monday first.
+    pub fn set_days(&mut self, days: Vec<Weekday>) {
+        self.days = sorted_days(days);
+    }
+
+    fn get_days(&self) -> Vec<Weekday> {
+        sorted_days(self.days.clone())
+    }
+
+    /// Whether the context applies on the given day, considering both its weekdays and its
+    /// active date range.
+    pub fn is_active_at(&self, day: NaiveDate) -> bool {
+        self.days.contains(&day.weekday())
+            && self.active_from.is_none_or(|from| day >= from)
+            && self.active_until.is_none_or(|until| day <= until)
+    }
+
+    /// Whether the two contexts can claim the same time. Their regular hours overlap when their
+    /// active date ranges intersect and their windows intersect on a shared weekday, where a
+    /// window that runs past midnight counts toward the next day after midnight. On dates either
+    /// context has an exception for, the hours that actually apply that day are compared too.
+    /// Windows that only touch don't overlap.
+    pub fn overlaps(&self, other: &Context) -> bool {
+        self.regular_hours_overlap(other)
+            || self
+                .exceptions
+                .iter()
+                .chain(other.exceptions.iter())
+                .any(|exception| {
+                    let (ours, theirs) = (
+                        self.spans_near(exception.date),
+                        other.spans_near(exception.date),
+                    );
+                    ours.iter().any(|(start, end)| {
+                        theirs
+                            .iter()
+                            .any(|(other_start, other_end)| start < other_end && other_start < end)
+                    })
+                })
+    }
+
+    fn regular_hours_overlap(&self, other: &Context) -> bool {
+        let ranges_intersect = self
+            .active_from
+            .zip(other.active_until)
+            .is_none_or(|(from, until)| from <= until)
+            && other
+                .active_from
+                .zip(self.active_until)
+                .is_none_or(|(from, until)| from <= until);
+
+        let other_spans = other.weekday_spans();
+        let windows_intersect = self.weekday_spans().iter().any(|(day, start, end)| {
+            other_spans
+                .iter()
+                .any(|(other_day, other_start, other_end)| {
+                    day == other_day && start < other_end && other_start < end
+                })
         });
 
-        return days;
+        ranges_intersect && windows_intersect
+    }
+
+    /// The regular hours as (weekday, start, end) spans in seconds from midnight. A window that
+    /// runs past midnight is split into its part on each side of midnight.
+    fn weekday_spans(&self) -> Vec<(Weekday, u32, u32)> {
+        let start = self.start.num_seconds_from_midnight();
+        let end = self.end.num_seconds_from_midnight();
+        let mut spans = vec![];
+        for day in &self.days {
+            if start < end {
+                spans.push((*day, start, end));
+            } else if start > end {
+                spans.push((*day, start, 24 * 60 * 60));
+                spans.push((day.succ(), 0, end));
+            }
+        }
+        spans
     }
 
-    pub fn get_timeblock(&self, day: NaiveDate) -> Option<TimeBlock> {
-        if self.days.contains(&day.weekday()) {
-            Some(TimeBlock::new(self.start, self.end, day, day))
+    /// The windows that apply on `day` and the day before it, as start and end moments, so a
+    /// window that runs past midnight into `day` is included.
+    fn spans_near(&self, day: NaiveDate) -> Vec<(NaiveDateTime, NaiveDateTime)> {
+        [day - Duration::days(1), day]
+            .into_iter()
+            .flat_map(|date| self.get_timeblock(date))
+            .map(|block| {
+                let start = block.start_date.and_time(block.start_time);
+                (start, start + window_length(&block))
+            })
+            .collect()
+    }
+
+    /// Adds an exception, replacing any existing exception for the same date.
+    pub fn add_exception(&mut self, exception: ContextException) {
+        self.exceptions.retain(|e| e.date != exception.date);
+        self.exceptions.push(exception);
+        self.exceptions.sort_by_key(|e| e.date);
+    }
+
+    fn get_exception(&self, day: NaiveDate) -> Option<&ContextException> {
+        self.exceptions.iter().find(|e| e.date == day)
+    }
+
+    pub fn has_exception(&self, day: NaiveDate) -> bool {
+        self.get_exception(day).is_some()
+    }
+
+    /// Resolves the context's windows on a given day, in start order. An exception for the day
+    /// takes precedence over the regular weekdays, hours, and active date range, except that an
+    /// extension adds to the regular window. Overlapping windows are merged, so there are two
+    /// windows only when an extension doesn't touch the regular hours.
+    pub fn get_timeblock(&self, day: NaiveDate) -> Vec<TimeBlock> {
+        let regular = if self.is_active_at(day) {
+            vec![TimeBlock::new(self.start, self.end, day, day)]
         } else {
-            None
+            vec![]
+        };
+
+        let exception = match self.get_exception(day) {
+            Some(exception) => exception,
+            None => return regular,
+        };
+        let window = TimeBlock::new(exception.start_time, exception.end_time, day, day);
+        match exception.mode {
+            ExceptionMode::Override => vec![window],
+            ExceptionMode::Cancel => vec![],
+            ExceptionMode::Extend => {
+                let mut windows = regular;
+                windows.push(window);
+                merge_windows(windows)
+            }
         }
     }
 
-    pub fn print(&self) {
-        println!("Context - {}", self.name);
-        print!("- Days: ");
+    /// The minutes this context is active over the seven days starting at `week_start`, with
+    /// exceptions and the active date range applied. Windows that run past midnight are counted
+    /// in full.
+    pub fn weekly_active_minutes(&self, week_start: NaiveDate) -> i64 {
+        (0..7)
+            .flat_map(|offset| self.get_timeblock(week_start + Duration::days(offset)))
+            .map(|block| window_length(&block).num_minutes())
+            .sum()
+    }
+
+    /// Describes each window shorter than `work`, which could never hold a work block: the
+    /// regular hours, then the windows on dates with exceptions.
+    pub fn short_windows(&self, work: Duration) -> Vec<String> {
+        let describe = |block: &TimeBlock| {
+            format!(
+                "{} to {}",
+                block.start_time.format("%H:%M"),
+                block.end_time.format("%H:%M")
+            )
+        };
+
+        let mut short = vec![];
+        let regular = TimeBlock::new(self.start, self.end, NaiveDate::MIN, NaiveDate::MIN);
+        if !self.days.is_empty() && window_length(&regular) < work {
+            short.push(describe(&regular));
+        }
+        for exception in &self.exceptions {
+            for block in self.get_timeblock(exception.date) {
+                if window_length(&block) < work {
+                    short.push(format!("{}, {}", exception.date, describe(&block)));
+                }
+            }
+        }
+        short
+    }
+
+    /// A one line description for listings, with the number of open tasks in the context.
+    pub fn summary(&self, open_tasks: usize) -> String {
+        let days: Vec<String> = self.get_days().iter().map(|day| day.to_string()).collect();
+        format!(
+            "{name} | {days} | {start} to {end} | {open_tasks} open {tasks}",
+            name = self.colorize(&self.name),
+            days = if days.is_empty() {
+                String::from("No days")
+            } else {
+                days.join(", ")
+            },
+            start = self.start.format("%H:%M"),
+            end = self.end.format("%H:%M"),
+            tasks = if open_tasks == 1 { "task" } else { "tasks" }
+        )
+    }
+
+    /// The details of the context, one per line.
+    pub fn render(&self) -> String {
+        let mut lines = vec![format!("Context - {}", self.colorize(&self.name))];
 
         if self.days.is_empty() {
-            println!("None Set");
+            lines.push("- Days: None Set".to_string());
         } else {
             let days: Vec<String> = self.get_days().iter().map(|day| day.to_string()).collect();
-            println!("{}", days.join(", "));
+            lines.push(format!("- Days: {}", days.join(", ")));
         }
 
-        println!("- Start Time: {}", self.start.format("%H:%M"));
-        println!("- End Time: {}", self.end.format("%H:%M"));
+        match (self.active_from, self.active_until) {
+            (Some(from), Some(until)) => lines.push(format!("- Active: {} to {}", from, until)),
+            (Some(from), None) => lines.push(format!("- Active: from {}", from)),
+            (None, Some(until)) => lines.push(format!("- Active: until {}", until)),
+            (None, None) => {}
+        }
 
-        let transition_minutes = self.transition.num_minutes();
-        if transition_minutes >= 60 {
-            println!(
-                "- Transition Time: {:.2} hours",
-                transition_minutes as f64 / 60.0
-            );
-        } else {
-            println!("- Transition Time: {} minutes", transition_minutes);
+        lines.push(format!("- Start Time: {}", self.start.format("%H:%M")));
+        lines.push(format!("- End Time: {}", self.end.format("%H:%M")));
+
+        lines.push(format!(
+            "- Transition Time: {}",
+            format_duration(self.transition)
+        ));
+
+        if let Some(color) = &self.color {
+            lines.push(format!("- Color: {}", color));
+        }
+
+        if self.focus_mode {
+            lines.push("- Focus Mode: On".to_string());
+        }
+
+        if let Some(max_tasks) = self.max_tasks_per_day {
+            lines.push(format!("- Max Tasks Per Day: {}", max_tasks));
+        }
+
+        if self.priority != 0 {
+            lines.push(format!("- Priority: {}", self.priority));
         }
 
         if !self.exceptions.is_empty() {
-            println!("- Exceptions:");
+            lines.push("- Exceptions:".to_string());
             for exception in &self.exceptions {
-                println!(
-                    "  * {}, {} to {}",
-                    exception.date,
-                    exception.start_time.format("%H:%M"),
-                    exception.end_time.format("%H:%M")
-                );
+                match exception.mode {
+                    ExceptionMode::Override => lines.push(format!(
+                        "  * {}, {} to {}",
+                        exception.date,
+                        exception.start_time.format("%H:%M"),
+                        exception.end_time.format("%H:%M")
+                    )),
+                    ExceptionMode::Cancel => {
+                        lines.push(format!("  * {}, cancelled", exception.date))
+                    }
+                    ExceptionMode::Extend => lines.push(format!(
+                        "  * {}, also {} to {}",
+                        exception.date,
+                        exception.start_time.format("%H:%M"),
+                        exception.end_time.format("%H:%M")
+                    )),
+                }
             }
         } else {
-            println!("- Exceptions: None");
+            lines.push("- Exceptions: None".to_string());
         }
+
+        lines.join("\n")
+    }
+
+    pub fn print(&self) {
+        println!("{}", self.render());
     }
 }
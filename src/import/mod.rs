@@ -0,0 +1,246 @@
+/// Bulk import of tasks.
+use chrono::{Duration, NaiveDate};
+
+use super::context::{Context, ContextException};
+use super::model::PreemptApp;
+use super::task::Task;
+
+const CSV_HEADER: [&str; 5] = ["name", "duration", "priority", "context", "description"];
+
+/// The outcome of an import. Rows that couldn't be imported are reported with their line number.
+pub struct ImportReport {
+    pub added: usize,
+    /// Rows folded into a task with the same name, when deduplicating.
+    pub merged: usize,
+    /// Rows left alone because what they would add is already there.
+    pub skipped: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+/// Splits a CSV row into at most `limit` trimmed columns. A column in double quotes may contain
+/// commas, with `""` standing for a quote inside it. Unquoted commas in the last column are kept
+/// as part of it.
+fn split_csv_row(row: &str, limit: usize) -> Result<Vec<String>, String> {
+    let mut columns = vec![];
+    let mut column = String::new();
+    let mut quoted = false;
+    let mut chars = row.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                column.push('"');
+                chars.next();
+            }
+            '"' if quoted => quoted = false,
+            '"' if column.trim().is_empty() => {
+                column.clear();
+                quoted = true;
+            }
+            ',' if !quoted => {
+                columns.push(std::mem::take(&mut column).trim().to_string());
+                if columns.len() == limit - 1 {
+                    let rest: String = chars.clone().collect();
+                    if !rest.trim_start().starts_with('"') {
+                        columns.push(rest.trim().to_string());
+                        return Ok(columns);
+                    }
+                }
+            }
+            c => column.push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quote".to_string());
+    }
+    columns.push(column.trim().to_string());
+
+    Ok(columns)
+}
+
+/// Parses a single CSV row into a task. Empty columns fall back to the same defaults as `add-task`.
+fn parse_csv_row(app: &PreemptApp, row: &str) -> Result<Task, String> {
+    let columns = split_csv_row(row, CSV_HEADER.len())?;
+    let columns: Vec<&str> = columns.iter().map(|c| c.as_str()).collect();
+
+    if columns.len() < CSV_HEADER.len() - 1 {
+        return Err(format!(
+            "expected {} columns, found {}",
+            CSV_HEADER.len(),
+            columns.len()
+        ));
+    }
+
+    let name = columns[0];
+    if name.is_empty() {
+        return Err("name is empty".to_string());
+    }
+
+    let duration = match columns[1] {
+        "" => None,
+        minutes => match minutes.parse::<i64>() {
+            Ok(minutes) if minutes >= 0 => Some(Duration::minutes(minutes)),
+            _ => return Err(format!("invalid duration '{}'", minutes)),
+        },
+    };
+
+    let priority = match columns[2] {
+        "" => 1,
+        priority => match priority.parse::<i32>() {
+            Ok(priority) if (0..=10).contains(&priority) => priority,
+            _ => return Err(format!("invalid priority '{}'", priority)),
+        },
+    };
+
+    let context = match columns[3] {
+        "" => None,
+        context => {
+            if app.get_context(context).is_none() {
+                return Err(format!("context '{}' doesn't exist", context));
+            }
+            Some(context.to_string())
+        }
+    };
+
+    let description = match columns.get(4) {
+        Some(description) if !description.is_empty() => description.to_string(),
+        _ => name.to_string(),
+    };
+
+    Ok(match duration {
+        Some(duration) => Task::new_with_duration(
+            name.to_string(),
+            description,
+            priority,
+            false,
+            duration,
+            context,
+        ),
+        None => Task::new(name.to_string(), description, priority, false, context),
+    })
+}
+
+/// Adds a task with default settings for each non-blank line. Duplicate names are skipped and
+/// reported.
+pub fn import_task_names(app: &mut PreemptApp, contents: &str) -> ImportReport {
+    let mut report = ImportReport {
+        added: 0,
+        merged: 0,
+        skipped: vec![],
+        errors: vec![],
+    };
+
+    for name in contents.lines().map(|line| line.trim()) {
+        if name.is_empty() {
+            continue;
+        }
+
+        let task = Task::new(name.to_string(), name.to_string(), 1, false, None);
+        match app.add_task(task) {
+            Ok(_) => report.added += 1,
+            Err(error) => report.errors.push(format!("'{}': {}", name, error)),
+        }
+    }
+
+    report
+}
+
+/// Imports tasks from CSV text with a `name,duration,priority,context,description` header.
+///
+/// Rows whose name collides with an existing task are skipped and reported like any other error.
+/// With `dedupe`, they're merged into that task instead, adding their duration and contexts.
+/// Names match ignoring case and spacing, as everywhere else.
+pub fn import_csv(app: &mut PreemptApp, contents: &str, dedupe: bool) -> ImportReport {
+    let mut report = ImportReport {
+        added: 0,
+        merged: 0,
+        skipped: vec![],
+        errors: vec![],
+    };
+
+    let mut lines = contents.lines().enumerate();
+
+    match lines.next() {
+        Some((_, header))
+            if header
+                .split(',')
+                .map(|c| c.trim().to_lowercase())
+                .eq(CSV_HEADER.iter().map(|c| c.to_string())) => {}
+        _ => {
+            report.errors.push(format!(
+                "line 1: expected header '{}'",
+                CSV_HEADER.join(",")
+            ));
+            return report;
+        }
+    }
+
+    for (index, row) in lines {
+        let line_number = index + 1;
+
+        if row.trim().is_empty() {
+            continue;
+        }
+
+        let task = match parse_csv_row(app, row) {
+            Ok(task) => task,
+            Err(error) => {
+                report
+                    .errors
+                    .push(format!("line {}: {}", line_number, error));
+                continue;
+            }
+        };
+        if dedupe {
+            if let Some(existing) = app.find_duplicate_mut(&task) {
+                existing.merge(&task);
+                report.merged += 1;
+                continue;
+            }
+        }
+
+        match app.add_task(task) {
+            Ok(_) => report.added += 1,
+            Err(error) => report
+                .errors
+                .push(format!("line {}: {}", line_number, error)),
+        }
+    }
+
+    report
+}
+
+/// Adds a cancellation exception to the context for each `YYYY-MM-DD` line. Blank lines are
+/// ignored, and dates that already have an exception keep it and are listed as skipped.
+pub fn import_holidays(context: &mut Context, contents: &str) -> ImportReport {
+    let mut report = ImportReport {
+        added: 0,
+        merged: 0,
+        skipped: vec![],
+        errors: vec![],
+    };
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        match NaiveDate::parse_from_str(line, "%Y-%m-%d") {
+            Ok(date) if context.has_exception(date) => report.skipped.push(format!(
+                "the existing exception on {} (line {})",
+                date,
+                index + 1
+            )),
+            Ok(date) => {
+                context.add_exception(ContextException::cancellation(date));
+                report.added += 1;
+            }
+            Err(_) => report
+                .errors
+                .push(format!("line {}: invalid date '{}'", index + 1, line)),
+        }
+    }
+
+    report
+}
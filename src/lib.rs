@@ -1,6 +1,13 @@
+pub mod cli;
+pub mod clock;
+pub mod config;
 pub mod context;
+pub mod import;
 pub mod location;
 pub mod model;
+pub mod readable;
 pub mod schedule;
+pub mod schema;
 pub mod task;
 pub mod timeblock;
+pub mod tui;
@@ -1,7 +1,7 @@
 use serde;
 
 /// The description of a location.
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema, Debug, Clone)]
 pub struct GeoFence {
     longitude: f64,
     latitude: f64,
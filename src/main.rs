@@ -1,246 +1,1253 @@
-use chrono::{Duration, NaiveTime, Weekday};
-use clap::{App, Arg, ArgMatches, SubCommand};
-use preempt::context::Context;
-use preempt::model::{load, save, PreemptApp};
-use preempt::schedule::print_schedule;
-use preempt::task::Task;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc, Weekday};
+use clap::ArgMatches;
+use clap_complete::{generate, Shell};
+use preempt::cli::{build_cli, parse_days, parse_duration, parse_reset};
+use preempt::clock;
+use preempt::config::{parse_utc_offset, Config};
+use preempt::context::{set_colors, Context, ContextException};
+use preempt::import::{import_csv, import_holidays, import_task_names};
+use preempt::model::{backup, load, save, DataLocation, DataLock, PreemptApp};
+use preempt::schedule::{
+    coalesce, current_block, day_header, diff, format_block_line, get_task_priority, next_block,
+    print_diff, print_schedule, print_summary, render_break_ratio, render_schedule,
+    render_standings, render_summary, render_unscheduled, to_compact, to_markdown, week_dates,
+    week_day_header, worked_on, BreakMode, ScheduleOptions, SchedulePreset, SchedulingStrategy,
+    UnscheduledTask,
+};
+use preempt::schema::data_schema;
+use preempt::task::{format_duration, name_key, DeadlineKind, Difficulty, DurationUnits, Task};
 use preempt::timeblock::TimeBlock;
+use preempt::tui::{self, TimelineView};
+use std::env;
+use std::io::{self, IsTerminal, Read};
+use std::path::{Path, PathBuf};
 
-fn build_add_task_arg(app: App) -> App {
-    app.subcommand(
-        SubCommand::with_name("add-task")
-            .about("Adds a new task")
-            .arg(
-                Arg::with_name("name")
-                    .long("name")
-                    .required(true)
-                    .help("The name of the task")
-                    .takes_value(true),
-            )
-            .arg(
-                Arg::with_name("duration")
-                    .long("duration")
-                    .help("The duration of the task in minutes")
-                    .takes_value(true),
-            )
-            .arg(
-                Arg::with_name("context")
-                    .long("context")
-                    .help("The context of the task")
-                    .takes_value(true),
-            )
-            .arg(
-                Arg::with_name("priority")
-                    .long("priority")
-                    .help("The priority of the task (0-10)")
-                    .takes_value(true),
-            ),
-    )
-}
-
-fn build_add_context_arg(app: App) -> App {
-    app.subcommand(
-        SubCommand::with_name("add-context")
-            .about("Adds a new context")
-            .arg(
-                Arg::with_name("name")
-                    .long("name")
-                    .required(true)
-                    .help("The name of the context")
-                    .takes_value(true),
-            )
-            .arg(
-                Arg::with_name("days")
-                    .long("days")
-                    .required(true)
-                    .help("The days of the week for the context. Excepts Sun, Mon, Tue, Wed, Thu, Fri, Sat day codes.")
-                    .takes_value(true),
-            )
-            .arg(
-                Arg::with_name("start")
-                    .long("start")
-                    .required(true)
-                    .help("The start time for the context")
-                    .takes_value(true),
-            )
-            .arg(
-                Arg::with_name("end")
-                    .long("end")
-                    .required(true)
-                    .help("The end time for the context")
-                    .takes_value(true),
-            )
-            .arg(
-                Arg::with_name("transition")
-                    .long("transition")
-                    .required(false)
-                    .help("The transition time between contexts")
-                    .takes_value(true).validator(|x| {
-                        x.parse::<i32>()
-                            .map(|_| ())
-                            .map_err(|_| String::from("The value must be an integer"))
-                    }),
-            ),
-    )
+/// Soft problems found while handling a command. They're printed as they come up, unless --strict
+/// is set, in which case they're held back for stderr and make the run fail.
+struct Warnings {
+    strict: bool,
+    messages: Vec<String>,
 }
 
-fn build_show_context_arg(app: App) -> App {
-    app.subcommand(
-        SubCommand::with_name("show-context")
-            .about("Shows details about a specific context")
-            .arg(
-                Arg::with_name("name")
-                    .required(true)
-                    .help("The name of the context"),
-            ),
-    )
+impl Warnings {
+    fn warn(&mut self, message: String) {
+        if self.strict {
+            self.messages.push(message);
+        } else {
+            println!("{}", message);
+        }
+    }
+}
+
+/// Why a command failed. Each kind exits with its own code, so scripts can tell them apart: 0 is
+/// success, 1 is a user error and 2 is a data or I/O error.
+enum Failure {
+    /// A bad argument, or a name that doesn't exist.
+    User(String),
+    /// The data file, or another file, couldn't be read or written.
+    Data(String),
+}
+
+impl Failure {
+    fn exit_code(&self) -> i32 {
+        match self {
+            Failure::User(_) => 1,
+            Failure::Data(_) => 2,
+        }
+    }
+
+    fn message(&self) -> &str {
+        match self {
+            Failure::User(message) | Failure::Data(message) => message,
+        }
+    }
+}
+
+impl From<&str> for Failure {
+    fn from(message: &str) -> Failure {
+        Failure::User(String::from(message))
+    }
 }
 
-fn build_timeline_arg(app: App) -> App {
-    app.subcommand(
-        SubCommand::with_name("timeline")
-            .about("Creates and shows a timeline incorporating the current tasks."),
-    )
+impl From<String> for Failure {
+    fn from(message: String) -> Failure {
+        Failure::User(message)
+    }
+}
+
+/// Builds a task from the arguments `add-task` and `plan` share.
+fn task_from_args(sub_m: &ArgMatches, app: &PreemptApp) -> Result<Task, Failure> {
+    let name = sub_m.value_of("name").unwrap(); // safe to unwrap because it's required without --from-stdin
+
+    let contexts: Vec<&str> = sub_m.values_of("context").into_iter().flatten().collect();
+    if contexts.iter().any(|name| app.get_context(name).is_none()) {
+        return Err("Context doesn't exist.".into());
+    }
+    let context = contexts.first().copied();
+
+    let priority = match sub_m.value_of("priority") {
+        Some(pri) => pri.parse::<i32>().unwrap(), // safe to unwrap because it's validated
+        None => 1,
+    };
+
+    let mut task = match sub_m.value_of("duration") {
+        Some(duration) => Task::new_with_duration(
+            name.to_string(),
+            name.to_string(),
+            priority,
+            false,
+            parse_duration(duration, app.schedule_options.pomodoro.work()).unwrap(), // safe to unwrap because it's validated
+            context.map(|name| name.to_string()),
+        ),
+        None => Task::new(
+            name.to_string(),
+            name.to_string(),
+            priority,
+            false,
+            context.map(|name| name.to_string()),
+        ),
+    };
+    task.set_contexts(contexts.iter().map(|name| name.to_string()).collect());
+    Ok(task)
 }
 
-fn handle_add_task(matches: &ArgMatches, app: &mut PreemptApp) -> Result<(), &'static str> {
+fn handle_add_task(matches: &ArgMatches, app: &mut PreemptApp) -> Result<(), Failure> {
     if let Some(sub_m) = matches.subcommand_matches("add-task") {
-        let name = sub_m.value_of("name").unwrap(); // safe to unwrap because it's required
+        if sub_m.is_present("from-stdin") {
+            let mut contents = String::new();
+            if io::stdin().read_to_string(&mut contents).is_err() {
+                return Err(Failure::Data(String::from("Couldn't read from stdin")));
+            }
 
-        let context: Option<&str> = sub_m.value_of("context");
+            let report = import_task_names(app, &contents);
+            if report.added == 0 && report.errors.is_empty() {
+                println!("No task names on stdin; nothing added");
+            } else {
+                println!("Added {} tasks", report.added);
+            }
+            for error in report.errors {
+                println!("Skipped {}", error);
+            }
+            return Ok(());
+        }
 
-        if context.is_some() {
-            match app.get_context(&context.unwrap().to_string()) {
-                Some(_) => {}
-                None => {
-                    return Err("Context doesn't exist.");
+        let mut a_task = task_from_args(sub_m, app)?;
+
+        if let Some(dependencies) = sub_m.values_of("depends-on") {
+            for dependency in dependencies {
+                if app.get_task(dependency, None).is_none() {
+                    return Err("Dependency doesn't exist.".into());
                 }
+                a_task.add_dependency(dependency);
             }
         }
 
-        let priority = match sub_m.value_of("priority") {
-            Some(pri) => pri.parse::<i32>().unwrap(),
-            None => 1,
-        };
-
-        let a_task = match sub_m.value_of("duration") {
-            Some(duration) => Task::new_with_duration(
-                name.to_string(),
-                name.to_string(),
-                priority,
-                false,
-                Duration::minutes(duration.parse::<i64>().unwrap()),
-                match context {
-                    Some(name) => Some(name.to_string()),
-                    None => None,
-                },
-            ),
-            None => Task::new(
-                name.to_string(),
-                name.to_string(),
-                priority,
-                false,
-                match context {
-                    Some(name) => Some(name.to_string()),
-                    None => None,
-                },
-            ),
-        };
+        a_task.set_deadline(
+            sub_m
+                .value_of("deadline")
+                .map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()), // safe to unwrap because it's validated
+        );
+        a_task.set_deadline_kind(match sub_m.value_of("deadline-kind") {
+            Some("hard") => Some(DeadlineKind::Hard),
+            Some(_) => Some(DeadlineKind::Soft),
+            None => None,
+        });
+        a_task.set_fixed_start(
+            sub_m
+                .value_of("at")
+                .map(|time| NaiveTime::parse_from_str(time, "%H:%M").unwrap()), // safe to unwrap because it's validated
+        );
+        a_task.set_min_block(
+            sub_m
+                .value_of("min-block")
+                .map(|min| parse_duration(min, app.schedule_options.pomodoro.work()).unwrap()), // safe to unwrap because it's validated
+        );
+        a_task.set_estimate_uncertainty(
+            sub_m
+                .value_of("uncertainty")
+                .map(|value| parse_duration(value, app.schedule_options.pomodoro.work()).unwrap()), // safe to unwrap because it's validated
+        );
+        a_task.set_order(
+            sub_m
+                .value_of("order")
+                .map(|order| order.parse::<i32>().unwrap()), // safe to unwrap because it's validated
+        );
+        a_task.set_difficulty(match sub_m.value_of("difficulty") {
+            Some("low") => Some(Difficulty::Low),
+            Some("high") => Some(Difficulty::High),
+            Some(_) => Some(Difficulty::Medium),
+            None => None,
+        });
+        a_task.set_reset(
+            sub_m
+                .value_of("reset")
+                .map(|reset| parse_reset(reset).unwrap()), // safe to unwrap because it's validated
+        );
 
         match app.add_task(a_task) {
-            Ok(_) => {}
-            Err(error) => return Err(error),
+            Ok(task) => println!("Added task '{}'", task.name),
+            Err(error) => return Err(error.into()),
         };
         return Ok(());
     }
     Ok(())
 }
 
-fn handle_add_context(matches: &ArgMatches, app: &mut PreemptApp) {
+fn handle_add_context(
+    matches: &ArgMatches,
+    app: &mut PreemptApp,
+    warnings: &mut Warnings,
+) -> Result<(), Failure> {
     if let Some(sub_m) = matches.subcommand_matches("add-context") {
         let name = sub_m.value_of("name").unwrap(); // safe to unwrap because it's required
 
-        let days = sub_m
-            .value_of("days")
-            .unwrap()
-            .split(',')
-            .map(|d| d.parse::<Weekday>()) // Implement a function to convert string to Weekday
-            .collect::<Result<Vec<Weekday>, _>>()
-            .unwrap_or_else(|_| vec![]); // default to empty vector if parsing fails
+        let transition = sub_m
+            .value_of("transition")
+            .map(|t| parse_duration(t, app.schedule_options.pomodoro.work()).unwrap()) // safe to unwrap because it's validated
+            .unwrap_or_else(|| Duration::minutes(0));
 
-        let start = NaiveTime::parse_from_str(sub_m.value_of("start").unwrap(), "%H:%M") // safe to unwrap because it's required
-            .unwrap_or_else(|_| {
-                NaiveTime::from_hms_opt(0, 0, 0).expect("Failed to create default start time")
-            }); // default to midnight if parsing fails
+        let mut new_context = if let Some(preset) = sub_m.value_of("preset") {
+            let mut context = Context::from_preset(preset, name)?;
+            context.transition = transition;
+            context
+        } else {
+            let days = sub_m.value_of("days").unwrap(); // safe to unwrap because it's required without a preset
+            let days = parse_days(days)?;
 
-        let end = NaiveTime::parse_from_str(sub_m.value_of("end").unwrap(), "%H:%M") // safe to unwrap because it's required
-            .unwrap_or_else(|_| {
-                NaiveTime::from_hms_opt(0, 0, 0).expect("Failed to create default end time")
-            }); // default to midnight if parsing fails
+            let start = NaiveTime::parse_from_str(sub_m.value_of("start").unwrap(), "%H:%M") // safe to unwrap because it's required without a preset
+                .unwrap_or_else(|_| {
+                    NaiveTime::from_hms_opt(0, 0, 0).expect("Failed to create default start time")
+                }); // default to midnight if parsing fails
 
+            let end = NaiveTime::parse_from_str(sub_m.value_of("end").unwrap(), "%H:%M") // safe to unwrap because it's required without a preset
+                .unwrap_or_else(|_| {
+                    NaiveTime::from_hms_opt(0, 0, 0).expect("Failed to create default end time")
+                }); // default to midnight if parsing fails
+
+            Context::try_new(name, days, start, end, transition)?
+        };
+        if let Some(color) = sub_m.value_of("color") {
+            new_context.set_color(color)?;
+        }
+        new_context.active_from = sub_m
+            .value_of("from")
+            .map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()); // safe to unwrap because it's validated
+        new_context.active_until = sub_m
+            .value_of("until")
+            .map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()); // safe to unwrap because it's validated
+        if let (Some(from), Some(until)) = (new_context.active_from, new_context.active_until) {
+            if from > until {
+                return Err("The --from date must not be after the --until date".into());
+            }
+        }
+
+        new_context.focus_mode = sub_m.is_present("focus");
+        new_context.max_tasks_per_day = sub_m
+            .value_of("max-tasks")
+            .map(|max| max.parse::<usize>().unwrap()); // safe to unwrap because it's validated
+        if let Some(priority) = sub_m.value_of("priority") {
+            new_context.priority = priority.parse::<i32>().unwrap(); // safe to unwrap because it's validated
+        }
+
+        let short_windows = app.short_window_warnings(&new_context);
+        for other in app.add_context(new_context)? {
+            warnings.warn(format!(
+                "Warning: '{}' overlaps the existing context '{}'",
+                name, other
+            ));
+        }
+        for short_window in short_windows {
+            warnings.warn(format!("Warning: {}", short_window));
+        }
+    }
+    Ok(())
+}
+
+fn handle_remove_context(matches: &ArgMatches, app: &mut PreemptApp) -> Result<(), Failure> {
+    if let Some(sub_m) = matches.subcommand_matches("remove-context") {
+        let name = sub_m.value_of("name").unwrap(); // safe to unwrap because it's required
+        app.remove_context(name)?;
+        println!("Removed context '{}'", name);
+    }
+    Ok(())
+}
+
+fn handle_list_contexts(matches: &ArgMatches, app: &PreemptApp) {
+    if let Some(sub_m) = matches.subcommand_matches("list-contexts") {
+        let mut contexts: Vec<(&Context, usize)> = app
+            .contexts()
+            .iter()
+            .map(|context| (context, app.context_task_count(context)))
+            .collect();
+        match sub_m.value_of("sort") {
+            Some("name") => contexts.sort_by_key(|(context, _)| name_key(&context.name)),
+            Some(_) => contexts.sort_by_key(|(_, count)| std::cmp::Reverse(*count)),
+            None => {}
+        }
+
+        if contexts.is_empty() {
+            println!("No contexts");
+        }
+        for (context, count) in contexts {
+            println!("{}", context.summary(count));
+        }
+    }
+}
+
+fn handle_show_context(matches: &ArgMatches, app: &mut PreemptApp) -> Result<(), Failure> {
+    if let Some(sub_m) = matches.subcommand_matches("show-context") {
+        let name = sub_m.value_of("name").unwrap(); // safe to unwrap because it's required
+
+        match app.get_context(name) {
+            Some(context) => {
+                context.print();
+
+                if let Some(date) = sub_m.value_of("date") {
+                    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(); // safe to unwrap because it's validated
+                    let windows: Vec<String> = context
+                        .get_timeblock(date)
+                        .iter()
+                        .map(|block| {
+                            format!(
+                                "{} to {}",
+                                block.start_time.format("%H:%M"),
+                                block.end_time.format("%H:%M")
+                            )
+                        })
+                        .collect();
+                    if windows.is_empty() {
+                        println!("- On {}: inactive", date);
+                    } else {
+                        println!("- On {}: {}", date, windows.join(", "));
+                    }
+                }
+            }
+            None => return Err(format!("No context by the name '{name}'").into()),
+        }
+    }
+    Ok(())
+}
+
+fn handle_add_exception(matches: &ArgMatches, app: &mut PreemptApp) -> Result<(), Failure> {
+    if let Some(sub_m) = matches.subcommand_matches("add-exception") {
+        // All of these are safe to unwrap because they're required and validated
+        let name = sub_m.value_of("context").unwrap();
+        let date = NaiveDate::parse_from_str(sub_m.value_of("date").unwrap(), "%Y-%m-%d").unwrap();
+        let time = |flag: &str| {
+            sub_m
+                .value_of(flag)
+                .map(|time| NaiveTime::parse_from_str(time, "%H:%M").unwrap())
+                .unwrap_or(NaiveTime::MIN)
+        };
+        let (start, end) = (time("start"), time("end"));
         let transition = sub_m
             .value_of("transition")
-            .map(|t| Duration::minutes(t.parse().unwrap_or(0)))
-            .unwrap_or_else(|| Duration::minutes(0)); // default to 0 minutes if parsing fails or not provided
+            .map(|t| parse_duration(t, app.schedule_options.pomodoro.work()).unwrap()) // safe to unwrap because it's validated
+            .unwrap_or_else(|| Duration::minutes(0));
+        let exception = match sub_m.value_of("mode") {
+            Some("cancel") => ContextException::cancellation(date),
+            Some("extend") => ContextException::extension(date, start, end, transition),
+            _ => ContextException::new(date, start, end, transition),
+        };
+
+        match app.get_context_mut(name) {
+            Some(context) => {
+                context.add_exception(exception);
+                println!("Added exception to '{}' on {}", context.name, date);
+            }
+            None => return Err(format!("No context by the name '{name}'").into()),
+        }
+    }
+    Ok(())
+}
+
+/// Starts from the stored options, or a named preset, and applies the command line flags on top.
+fn timeline_options(sub_m: &ArgMatches, app: &PreemptApp) -> Result<ScheduleOptions, String> {
+    let mut options = match sub_m.value_of("preset") {
+        Some(name) => {
+            let preset = app
+                .get_preset(name)
+                .ok_or_else(|| format!("No preset named '{}'", name))?;
+            let mut options = preset.options.clone();
+            options.pomodoro = preset.pomodoro;
+            options
+        }
+        None => app.schedule_options.clone(),
+    };
+    apply_schedule_flags(sub_m, &mut options);
+    if let Some(names) = sub_m.values_of("context") {
+        let mut only = vec![];
+        for name in names {
+            match app.get_context(name) {
+                Some(context) => only.push(context.name.clone()),
+                None => return Err(format!("No context by the name '{}'", name)),
+            }
+        }
+        options.only_contexts = Some(only);
+    }
+    options.show_free = sub_m.is_present("show-free");
+    Ok(options)
+}
+
+fn apply_schedule_flags(sub_m: &ArgMatches, options: &mut ScheduleOptions) {
+    if sub_m.is_present("shuffle-low") {
+        options.shuffle_low = true;
+    }
+    match sub_m.value_of("strategy") {
+        Some("weighted") => options.strategy = SchedulingStrategy::WeightedScore,
+        Some(_) => options.strategy = SchedulingStrategy::Multilevel,
+        None => {}
+    }
+    if let Some(days) = sub_m.value_of("priority-boost") {
+        options.aging_threshold_days = Some(days.parse::<i64>().unwrap()); // safe to unwrap because it's validated
+    }
+    if sub_m.is_present("weighted-low") {
+        options.weighted_low = true;
+    }
+    if sub_m.is_present("skip-transition-break") {
+        options.skip_transition_break = true;
+    }
+    match sub_m.value_of("breaks-as") {
+        Some("free") => options.breaks = BreakMode::Free,
+        Some(_) => options.breaks = BreakMode::Explicit,
+        None => {}
+    }
+    if let Some(minutes) = sub_m.value_of("align") {
+        options.align_to = Some(Duration::minutes(minutes.parse::<i64>().unwrap()));
+        // safe to unwrap because it's validated
+    }
+    if let Some(max) = sub_m.value_of("max-consecutive") {
+        options.max_consecutive_blocks = Some(max.parse::<usize>().unwrap()); // safe to unwrap because it's validated
+    }
+}
+
+fn handle_save_preset(matches: &ArgMatches, app: &mut PreemptApp) -> Result<(), Failure> {
+    if let Some(sub_m) = matches.subcommand_matches("save-preset") {
+        let name = sub_m.value_of("name").unwrap(); // safe to unwrap because it's required
+        let mut options = app.schedule_options.clone();
+        apply_schedule_flags(sub_m, &mut options);
+
+        // safe to unwrap these because they're validated
+        let minutes = |flag: &str| {
+            sub_m
+                .value_of(flag)
+                .map(|value| value.parse::<i64>().unwrap())
+        };
+        let mut pomodoro = options.pomodoro;
+        if let Some(value) = minutes("work-minutes") {
+            pomodoro.work_minutes = value;
+        }
+        if let Some(value) = minutes("short-break-minutes") {
+            pomodoro.short_break_minutes = value;
+        }
+        if let Some(value) = minutes("long-break-minutes") {
+            pomodoro.long_break_minutes = value;
+        }
+        if let Some(value) = minutes("long-break-every") {
+            pomodoro.long_break_every = value;
+        }
+        pomodoro.validate()?;
 
-        let new_context = Context::new(name, days, start, end, transition);
-        match app.add_context(new_context) {
-            Ok(_) => {}
-            Err(error) => {
-                println!("{}", error)
+        app.save_preset(SchedulePreset {
+            name: String::from(name),
+            options,
+            pomodoro,
+        });
+        println!("Saved preset '{}'", name);
+    }
+    Ok(())
+}
+
+/// Writes command output to a file, creating its parent directories as needed.
+fn write_output(path: &str, contents: &str) -> Result<(), String> {
+    let path = Path::new(path);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|error| format!("Couldn't create '{}': {}", parent.display(), error))?;
+    }
+    std::fs::write(path, contents)
+        .map_err(|error| format!("Couldn't write '{}': {}", path.display(), error))
+}
+
+fn handle_timeline(
+    matches: &ArgMatches,
+    app: &mut PreemptApp,
+    warnings: &mut Warnings,
+) -> Result<(), Failure> {
+    if let Some(sub_m) = matches.subcommand_matches("timeline") {
+        if sub_m.is_present("output-file") {
+            // The file gets the plain text, whatever stdout is.
+            set_colors(false);
+        }
+        let limit = sub_m
+            .value_of("limit")
+            .map(|limit| limit.parse::<usize>().unwrap()); // safe to unwrap because it's validated
+        let options = timeline_options(sub_m, app)?;
+
+        let today = clock::today();
+        let week = sub_m.is_present("week");
+        let dates = if week {
+            let week_start = match sub_m.value_of("week-start") {
+                Some(day) => day.parse::<Weekday>().unwrap(), // safe to unwrap because it's validated
+                None => app.week_start,
+            };
+            week_dates(today, week_start)
+        } else if let Some(date) = sub_m.value_of("date") {
+            vec![NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()] // safe to unwrap because it's validated
+        } else {
+            vec![today]
+        };
+
+        let save = sub_m.is_present("save");
+        let until_done = sub_m.is_present("until-done");
+        let (dates, mut results, left) = if until_done {
+            let start = dates[0];
+            let max_days = sub_m
+                .value_of("max-days")
+                .unwrap()
+                .parse::<usize>()
+                .unwrap(); // safe to unwrap because it has a validated default
+            let (results, left) = app.plan_until_done(start, max_days, &options);
+            let dates = start.iter_days().take(results.len()).collect();
+            (dates, results, left)
+        } else {
+            let results = app.schedule_days(&dates, &options);
+            (dates, results, vec![])
+        };
+        // Locked days show what was locked, whatever has changed since.
+        for (date, result) in dates.iter().zip(results.iter_mut()) {
+            if let Some(locked) = app.locked_schedule(*date) {
+                result.blocks = locked.to_vec();
+                result.unscheduled.retain(|unscheduled| {
+                    app.get_task(&unscheduled.name, unscheduled.context.as_deref())
+                        .is_none_or(|task| worked_on(locked, task).is_zero())
+                });
+            }
+        }
+        let mut output = String::new();
+        for (i, (date, result)) in dates.iter().zip(results).enumerate() {
+            if week || until_done {
+                if i > 0 {
+                    output.push('\n');
+                }
+                let header = if sub_m.is_present("week-numbers") {
+                    week_day_header(*date)
+                } else {
+                    day_header(*date)
+                };
+                output.push_str(&format!("# {}\n", header));
             }
+
+            let mut schedule = result.blocks.clone();
+            if sub_m.is_present("coalesce") {
+                schedule = coalesce(schedule);
+            }
+            if save {
+                app.save_schedule(*date, schedule.clone());
+            }
+            // Soft deadlines only warn, even in strict mode. Hard ones fail it.
+            for risk in app.deadline_risks(*date, &schedule) {
+                match risk.kind {
+                    DeadlineKind::Hard => warnings.warn(format!("Error: {}", risk)),
+                    DeadlineKind::Soft => println!("Warning: {}", risk),
+                }
+            }
+            if warnings.strict {
+                for task in &result.unscheduled {
+                    warnings.warn(format!(
+                        "Unscheduled on {}: {} ({})",
+                        date, task.name, task.reason
+                    ));
+                }
+            }
+            if sub_m.is_present("compact") {
+                let shown = limit.unwrap_or(schedule.len()).min(schedule.len());
+                output.push_str(&to_compact(&schedule[..shown]));
+                continue;
+            }
+            if app.locked_schedule(*date).is_some() {
+                output.push_str("Locked with lock-schedule; unlock-schedule plans it again\n");
+            }
+            if sub_m.is_present("explain-unscheduled") {
+                let standings = app.explain_schedule(*date, &options, &result);
+                output.push_str(&render_day(schedule, &[], limit, app));
+                output.push_str(&render_standings(&standings));
+                continue;
+            }
+            output.push_str(&render_day(schedule, &result.unscheduled, limit, app));
+        }
+        if until_done && dates.is_empty() {
+            output.push_str("Nothing left to plan.\n");
+        }
+        if !left.is_empty() {
+            output.push_str(&format!("\nLeft after {} days:\n", dates.len()));
+            for (name, duration) in &left {
+                output.push_str(&format!(
+                    "  {} ({} left)\n",
+                    name,
+                    format_duration(*duration)
+                ));
+            }
+        }
+
+        match sub_m.value_of("output-file") {
+            Some(path) => {
+                write_output(path, &output).map_err(Failure::Data)?;
+                println!("Wrote the timeline to '{}'", path);
+            }
+            None => print!("{}", output),
+        }
+        if save {
+            for date in &dates {
+                println!("Saved the schedule for {}", date);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A day's timeline as `timeline` shows it, followed by its summary and anything left out.
+fn render_day(
+    schedule: Vec<TimeBlock>,
+    unscheduled: &[UnscheduledTask],
+    limit: Option<usize>,
+    app: &PreemptApp,
+) -> String {
+    let summary = app.summarize(&schedule);
+    let mut output = render_schedule(schedule, limit, app.contexts());
+    output.push_str(&render_summary(&summary));
+    output.push('\n');
+    output.push_str(&render_unscheduled(unscheduled));
+    output
+}
+
+fn handle_plan(matches: &ArgMatches, app: &mut PreemptApp) -> Result<(), Failure> {
+    if let Some(sub_m) = matches.subcommand_matches("plan") {
+        let task = task_from_args(sub_m, app)?;
+        let name = app.add_task(task)?.name.clone();
+        println!("Added task '{}'", name);
+
+        let result = app.schedule_for(clock::today(), &app.schedule_options);
+        print!(
+            "{}",
+            render_day(result.blocks, &result.unscheduled, None, app)
+        );
+    }
+    Ok(())
+}
+
+fn handle_next(matches: &ArgMatches, app: &PreemptApp) {
+    if matches.subcommand_matches("next").is_some() {
+        let now = clock::now();
+        let schedule = app.build_schedule_for(now.date(), &app.schedule_options);
+
+        match current_block(&schedule, now) {
+            Some(block) => println!("Now:  {}", format_block_line(block)),
+            None => println!("Now:  nothing scheduled"),
+        }
+        match next_block(&schedule, now) {
+            Some(block) => println!("Next: {}", format_block_line(block)),
+            None => println!("Next: nothing else today"),
+        }
+    }
+}
+
+/// Shows today's timeline full screen, scrolling as keys are pressed until q. Without a terminal,
+/// prints the first `--height` blocks as text.
+fn handle_tui(matches: &ArgMatches, app: &PreemptApp) -> Result<(), Failure> {
+    if let Some(sub_m) = matches.subcommand_matches("tui") {
+        let height = sub_m.value_of("height").unwrap().parse::<usize>().unwrap(); // safe to unwrap because it has a validated default
+        let now = clock::now();
+        let schedule = match app.locked_schedule(now.date()) {
+            Some(locked) => locked.to_vec(),
+            None => app.build_schedule_for(now.date(), &app.schedule_options),
         };
+
+        let view = TimelineView::new(schedule, now, height);
+        // Without a terminal there's nothing to scroll, so the first frame is printed as text.
+        if !io::stdout().is_terminal() {
+            print!("{}", view.render());
+            return Ok(());
+        }
+        tui::run(view).map_err(|error| format!("Couldn't run the terminal view: {}", error))?;
     }
+    Ok(())
 }
 
-fn handle_show_context(matches: &ArgMatches, app: &mut PreemptApp) {
-    if let Some(sub_m) = matches.subcommand_matches("show-context") {
+fn handle_stats(matches: &ArgMatches, app: &PreemptApp) {
+    if let Some(sub_m) = matches.subcommand_matches("stats") {
+        let today = clock::today();
+        if sub_m.is_present("schedule") {
+            println!(
+                "{}: {}",
+                day_header(today),
+                render_break_ratio(&app.summary_on(today))
+            );
+            return;
+        }
+        if !sub_m.is_present("week") {
+            println!(
+                "{}: {} of work",
+                day_header(today),
+                format_duration(app.work_on(today))
+            );
+            return;
+        }
+
+        let dates = week_dates(today, app.week_start);
+        for date in &dates {
+            println!(
+                "{}: {} of work",
+                day_header(*date),
+                format_duration(app.work_on(*date))
+            );
+        }
+        match app.busiest_day(dates[0]) {
+            (_, work) if work.is_zero() => println!("Nothing scheduled this week"),
+            (date, work) => println!(
+                "Busiest day: {} with {}",
+                day_header(date),
+                format_duration(work)
+            ),
+        }
+    }
+}
+
+fn handle_show_schedule(matches: &ArgMatches, app: &PreemptApp) -> Result<(), Failure> {
+    if let Some(sub_m) = matches.subcommand_matches("show-schedule") {
+        let date = match sub_m.value_of("date") {
+            Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(), // safe to unwrap because it's validated
+            None => clock::today(),
+        };
+
+        match app.saved_schedule(date) {
+            Some(schedule) => {
+                let summary = app.summarize(schedule);
+                print_schedule(schedule.to_vec(), None, app.contexts());
+                print_summary(&summary);
+            }
+            None => return Err(format!("No saved schedule for {}", date).into()),
+        }
+    }
+    Ok(())
+}
+
+fn handle_lock_schedule(matches: &ArgMatches, app: &mut PreemptApp) -> Result<(), Failure> {
+    if let Some(sub_m) = matches.subcommand_matches("lock-schedule") {
+        let date = match sub_m.value_of("date") {
+            Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(), // safe to unwrap because it's validated
+            None => clock::today(),
+        };
+
+        let blocks = app.schedule_for(date, &app.schedule_options).blocks;
+        app.lock_schedule(date, blocks)?;
+        println!("Locked the schedule for {}", date);
+    }
+    Ok(())
+}
+
+fn handle_unlock_schedule(matches: &ArgMatches, app: &mut PreemptApp) -> Result<(), Failure> {
+    if let Some(sub_m) = matches.subcommand_matches("unlock-schedule") {
+        let date = match sub_m.value_of("date") {
+            Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(), // safe to unwrap because it's validated
+            None => clock::today(),
+        };
+
+        app.unlock_schedule(date)?;
+        println!("Unlocked the schedule for {}", date);
+    }
+    Ok(())
+}
+
+fn handle_diff_schedule(matches: &ArgMatches, app: &PreemptApp) -> Result<(), Failure> {
+    if let Some(sub_m) = matches.subcommand_matches("diff-schedule") {
+        let date = match sub_m.value_of("date") {
+            Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(), // safe to unwrap because it's validated
+            None => clock::today(),
+        };
+
+        match app.saved_schedule(date) {
+            Some(saved) => {
+                let fresh = app.build_schedule_for(date, &app.schedule_options);
+                print_diff(&diff(saved, &fresh));
+            }
+            None => return Err(format!("No saved schedule for {}", date).into()),
+        }
+    }
+    Ok(())
+}
+
+fn handle_export_md(matches: &ArgMatches, app: &PreemptApp) -> Result<(), Failure> {
+    if let Some(sub_m) = matches.subcommand_matches("export-md") {
+        let path = sub_m.value_of("output").unwrap(); // safe to unwrap because it's required
+        let markdown = to_markdown(&app.build_schedule(&app.schedule_options));
+
+        write_output(path, &markdown).map_err(Failure::Data)?;
+        println!("Exported schedule to '{}'", path);
+    }
+    Ok(())
+}
+
+fn handle_today(matches: &ArgMatches, app: &PreemptApp) {
+    if matches.subcommand_matches("today").is_some() {
+        let tasks = app.tasks_due_today(clock::today());
+
+        if tasks.is_empty() {
+            println!("Nothing on your plate today");
+        }
+        for task in tasks {
+            task.print_summary();
+        }
+    }
+}
+
+fn handle_list_tasks(matches: &ArgMatches, app: &PreemptApp) {
+    if let Some(sub_m) = matches.subcommand_matches("list-tasks") {
+        let mut tasks = app.list_tasks(sub_m.is_present("include-archived"));
+        if let Some(since) = sub_m.value_of("since") {
+            let since = NaiveDate::parse_from_str(since, "%Y-%m-%d").unwrap(); // safe to unwrap because it's validated
+            tasks.retain(|task| task.created().date() >= since);
+        }
+        if sub_m.is_present("snoozed") {
+            let today = clock::today();
+            tasks.retain(|task| task.is_snoozed_on(today));
+        }
+        if sub_m.value_of("sort") == Some("created") {
+            tasks.sort_by_key(|task| task.created());
+        }
+
+        if tasks.is_empty() {
+            println!("No tasks");
+        }
+        let units = match sub_m.value_of("units") {
+            Some("pomodoros") => DurationUnits::Pomodoros(app.schedule_options.pomodoro.work()),
+            _ => DurationUnits::Minutes,
+        };
+        for task in tasks {
+            if sub_m.is_present("explain-priority") {
+                println!(
+                    "{} | class {}",
+                    task.summary_in(units),
+                    get_task_priority(task)
+                );
+            } else {
+                task.print_summary_in(units);
+            }
+        }
+    }
+}
+
+fn handle_show_task(matches: &ArgMatches, app: &PreemptApp) -> Result<(), Failure> {
+    if let Some(sub_m) = matches.subcommand_matches("show-task") {
+        let name = sub_m.value_of("name").unwrap(); // safe to unwrap because it's required
+        let units = match sub_m.value_of("units") {
+            Some("pomodoros") => DurationUnits::Pomodoros(app.schedule_options.pomodoro.work()),
+            _ => DurationUnits::Minutes,
+        };
+
+        match app.get_task(name, sub_m.value_of("context")) {
+            Some(task) => task.print(clock::now(), units),
+            None => return Err(format!("No task by the name '{name}'").into()),
+        }
+    }
+    Ok(())
+}
+
+fn handle_archive_task(matches: &ArgMatches, app: &mut PreemptApp) -> Result<(), Failure> {
+    if let Some(sub_m) = matches.subcommand_matches("archive-task") {
+        let name = sub_m.value_of("name").unwrap(); // safe to unwrap because it's required
+
+        app.archive_task(name, sub_m.value_of("context"))?;
+        println!("Archived task '{}'", name);
+    }
+    Ok(())
+}
+
+fn handle_remove_task(matches: &ArgMatches, app: &mut PreemptApp) -> Result<(), Failure> {
+    if let Some(sub_m) = matches.subcommand_matches("remove-task") {
+        let name = sub_m.value_of("name").unwrap(); // safe to unwrap because it's required
+
+        app.remove_task(name, sub_m.value_of("context"))?;
+        println!("Removed task '{}'", name);
+    }
+    Ok(())
+}
+
+fn handle_snooze_task(matches: &ArgMatches, app: &mut PreemptApp) -> Result<(), Failure> {
+    if let Some(sub_m) = matches.subcommand_matches("snooze-task") {
+        let name = sub_m.value_of("name").unwrap(); // safe to unwrap because it's required
+        let until =
+            NaiveDate::parse_from_str(sub_m.value_of("until").unwrap(), "%Y-%m-%d").unwrap(); // safe to unwrap because it's required and validated
+
+        if until <= clock::today() {
+            return Err("The date must be after today.".into());
+        }
+        app.snooze_task(name, sub_m.value_of("context"), until)?;
+        println!("Snoozed '{}' until {}", name, until);
+    }
+    Ok(())
+}
+
+fn handle_reorder_task(matches: &ArgMatches, app: &mut PreemptApp) -> Result<(), Failure> {
+    if let Some(sub_m) = matches.subcommand_matches("reorder-task") {
+        let name = sub_m.value_of("name").unwrap(); // safe to unwrap because it's required
+        let order = sub_m
+            .value_of("order")
+            .map(|order| order.parse::<i32>().unwrap()); // safe to unwrap because it's validated
+
+        app.reorder_task(name, sub_m.value_of("context"), order)?;
+        match order {
+            Some(order) => println!("Set the order of '{}' to {}", name, order),
+            None => println!("Cleared the order of '{}'", name),
+        }
+    }
+    Ok(())
+}
+
+fn handle_deps(matches: &ArgMatches, app: &PreemptApp) -> Result<(), Failure> {
+    if let Some(sub_m) = matches.subcommand_matches("deps") {
+        let name = sub_m.value_of("task").unwrap(); // safe to unwrap because it's required
+
+        for line in app.dependency_tree(name)? {
+            println!("{}", line);
+        }
+    }
+    Ok(())
+}
+
+fn handle_split_task(matches: &ArgMatches, app: &mut PreemptApp) -> Result<(), Failure> {
+    if let Some(sub_m) = matches.subcommand_matches("split-task") {
         let name = sub_m.value_of("name").unwrap(); // safe to unwrap because it's required
+        let n = sub_m.value_of("into").unwrap().parse::<usize>().unwrap(); // safe to unwrap because it's required and validated
 
-        match app.get_context(&name.to_string()) {
-            Some(context) => context.print(),
-            None => {
-                println!("No context by the name '{name}'");
+        let pieces = app.split_task(name, sub_m.value_of("context"), n)?;
+        println!("Split '{}' into:", name);
+        for piece in pieces {
+            piece.print_summary();
+        }
+    }
+    Ok(())
+}
+
+fn handle_roll_over(matches: &ArgMatches, app: &mut PreemptApp) -> Result<(), Failure> {
+    if let Some(sub_m) = matches.subcommand_matches("roll-over") {
+        let today = clock::today();
+        // Dates are safe to unwrap because they're validated
+        let from = sub_m
+            .value_of("from")
+            .map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap())
+            .unwrap_or(today - Duration::days(1));
+        let to = sub_m
+            .value_of("to")
+            .map(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap())
+            .unwrap_or(today);
+
+        let carried = app.reschedule_incomplete(from, to)?;
+        if carried.is_empty() {
+            println!("Nothing to carry forward from {}", from);
+        } else {
+            println!("Carried forward to {}:", to);
+            for name in carried {
+                println!("- {}", name);
             }
         }
     }
+    Ok(())
 }
 
-fn handle_timeline(matches: &ArgMatches, app: &PreemptApp) {
-    if let Some(sub_m) = matches.subcommand_matches("timeline") {
-        print_schedule(app.build_schedule());
+/// Reads a file named on the command line.
+fn read_input(path: &str) -> Result<String, Failure> {
+    std::fs::read_to_string(path)
+        .map_err(|error| Failure::Data(format!("Couldn't read '{}': {}", path, error)))
+}
+
+fn handle_import_csv(matches: &ArgMatches, app: &mut PreemptApp) -> Result<(), Failure> {
+    if let Some(sub_m) = matches.subcommand_matches("import-csv") {
+        let path = sub_m.value_of("file").unwrap(); // safe to unwrap because it's required
+
+        let contents = read_input(path)?;
+
+        let report = import_csv(app, &contents, sub_m.is_present("dedupe"));
+        println!("Imported {} tasks", report.added);
+        if report.merged > 0 {
+            println!("Merged {} duplicates", report.merged);
+        }
+        for error in report.errors {
+            println!("Skipped {}", error);
+        }
+    }
+    Ok(())
+}
+
+fn handle_import_holidays(matches: &ArgMatches, app: &mut PreemptApp) -> Result<(), Failure> {
+    if let Some(sub_m) = matches.subcommand_matches("import-holidays") {
+        let context_name = sub_m.value_of("context").unwrap(); // safe to unwrap because it's required
+        let path = sub_m.value_of("file").unwrap(); // safe to unwrap because it's required
+
+        let contents = read_input(path)?;
+
+        match app.get_context_mut(context_name) {
+            Some(context) => {
+                let report = import_holidays(context, &contents);
+                println!("Cancelled '{}' on {} days", context.name, report.added);
+                for skipped in report.skipped {
+                    println!("Kept {}", skipped);
+                }
+                for error in report.errors {
+                    println!("Skipped {}", error);
+                }
+            }
+            None => return Err(format!("No context by the name '{}'", context_name).into()),
+        }
+    }
+    Ok(())
+}
+
+/// Writes a completion script to stdout. Returns true if the completion subcommand was used.
+fn handle_completion(matches: &ArgMatches) -> bool {
+    if let Some(sub_m) = matches.subcommand_matches("completion") {
+        let shell = match sub_m.value_of("shell").unwrap() {
+            // safe to unwrap because it's required
+            "zsh" => Shell::Zsh,
+            "fish" => Shell::Fish,
+            _ => Shell::Bash,
+        };
+        generate(shell, &mut build_cli(), "preempt", &mut io::stdout());
+        return true;
+    }
+    false
+}
+
+/// Prints the data file's JSON Schema. Returns true if the schema subcommand was used.
+fn handle_schema(matches: &ArgMatches) -> bool {
+    if matches.subcommand_matches("schema").is_some() {
+        print!("{}", data_schema());
+        return true;
     }
+    false
+}
+
+/// Backs up the data file and replaces everything with an empty app, but only with --confirm.
+fn handle_purge(
+    matches: &ArgMatches,
+    app: &mut PreemptApp,
+    location: &DataLocation,
+) -> Result<(), Failure> {
+    if let Some(sub_m) = matches.subcommand_matches("purge") {
+        if !sub_m.is_present("confirm") {
+            return Err("Refusing to purge without --confirm. This removes every task, context and saved schedule.".into());
+        }
+
+        let backup_path = backup(location)
+            .map_err(|error| Failure::Data(format!("{}; nothing was purged", error)))?;
+        if let Some(backup_path) = backup_path {
+            println!("Backed up the data file to {}", backup_path.display());
+        }
+        *app = PreemptApp::new();
+        println!("Purged all data");
+    }
+    Ok(())
+}
+
+/// Reports problems in the loaded data. Returns whether the check subcommand was used, and if so
+/// whether the data is healthy.
+fn handle_check(matches: &ArgMatches, app: &PreemptApp) -> Option<bool> {
+    matches.subcommand_matches("check")?;
+    let problems = app.check();
+    if problems.is_empty() {
+        println!("No problems found");
+    }
+    for problem in &problems {
+        println!("- {}", problem);
+    }
+    Some(problems.is_empty())
+}
+
+/// Prints build and data location details. Returns true if the version subcommand was used.
+fn handle_version(matches: &ArgMatches, location: &DataLocation) -> bool {
+    if matches.subcommand_matches("version").is_some() {
+        println!("preempt {}", env!("CARGO_PKG_VERSION"));
+        println!("Data file: {}", location.path().display());
+        return true;
+    }
+    false
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let app = App::new("preempt").about("A scheduler for humans.");
-    let app = build_add_task_arg(app);
-    let app = build_add_context_arg(app);
-    let app = build_show_context_arg(app);
-    let app = build_timeline_arg(app);
-    let matches = app.get_matches();
-
-    let mut preempt_app: PreemptApp = match load() {
+    // Usage errors are user errors. Help and --version aren't errors at all.
+    let matches = match build_cli().try_get_matches() {
+        Ok(matches) => matches,
+        Err(error) => {
+            let _ = error.print();
+            std::process::exit(if error.use_stderr() { 1 } else { 0 });
+        }
+    };
+
+    // Completion scripts and the schema go straight to stdout, so skip loading and saving data
+    // entirely.
+    if handle_completion(&matches) || handle_schema(&matches) {
+        return Ok(());
+    }
+
+    // Context colors are only for people, never for files or other programs.
+    set_colors(io::stdout().is_terminal());
+
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(error) => {
+            println!("{}", error);
+            Config::default()
+        }
+    };
+
+    // Flags win over the environment, which wins over the config file.
+    let data_dir: Option<PathBuf> = matches
+        .value_of("data-dir")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("PREEMPT_DATA_DIR").map(PathBuf::from))
+        .or_else(|| config.data_dir.clone());
+    let location = DataLocation::new(data_dir, matches.value_of("profile").map(String::from));
+    if location.is_fallback() {
+        eprintln!(
+            "Warning: couldn't find a data directory for this platform, using {}. Use --data-dir or PREEMPT_DATA_DIR to choose one.",
+            location.path().display()
+        );
+    }
+
+    if let Some(timezone) = matches.value_of("timezone").or(config.timezone.as_deref()) {
+        // safe to unwrap because both the flag and the config file are validated
+        clock::set_utc_offset(parse_utc_offset(timezone).unwrap());
+    }
+    if let Some(now) = matches.value_of("now") {
+        clock::set_now(
+            DateTime::parse_from_rfc3339(now)
+                .unwrap()
+                .with_timezone(&Utc),
+        ); // safe to unwrap because it's validated
+    }
+
+    if handle_version(&matches, &location) {
+        return Ok(());
+    }
+
+    // Held until everything is saved, and released before exiting.
+    let lock = match DataLock::acquire(&location) {
+        Ok(lock) => lock,
+        Err(error) => {
+            let failure = Failure::Data(error);
+            eprintln!("{}", failure.message());
+            std::process::exit(failure.exit_code());
+        }
+    };
+    let code = run(&matches, &config, &location);
+    drop(lock);
+    if code != 0 {
+        std::process::exit(code);
+    }
+
+    Ok(())
+}
+
+/// Loads the data, runs the command and saves the result, returning the exit code.
+fn run(matches: &ArgMatches, config: &Config, location: &DataLocation) -> i32 {
+    let mut warnings = Warnings {
+        strict: matches.is_present("strict"),
+        messages: vec![],
+    };
+
+    let mut preempt_app: PreemptApp = match load(location) {
         Ok(data) => data,
         Err(e) => {
             println!("Failed to load data: {}", e);
-            // Create a new, empty PreemptApp if loading fails
+            let missing = e
+                .downcast_ref::<io::Error>()
+                .is_some_and(|error| error.kind() == io::ErrorKind::NotFound);
+            if !missing {
+                // Saving now would overwrite the data that couldn't be read.
+                return Failure::Data(String::new()).exit_code();
+            }
+            // Create a new, empty PreemptApp if there's no data yet
             PreemptApp::new()
         }
     };
+    preempt_app.schedule_options.pomodoro = config.pomodoro.unwrap_or_default();
+    if let Some(scope) = config.unique_scope {
+        preempt_app.unique_scope = scope;
+    }
+    // Problems the file already had shouldn't stop it being saved, or it could never be fixed.
+    let known_problems = preempt_app.validate().err().unwrap_or_default();
 
-    match handle_add_task(&matches, &mut preempt_app) {
-        Ok(_) => (),
-        Err(error) => println!("{}", error),
+    // Checking only reads, so there's nothing to save afterwards.
+    match handle_check(matches, &preempt_app) {
+        Some(true) => return 0,
+        Some(false) => return Failure::Data(String::new()).exit_code(),
+        None => {}
+    }
+
+    let results = [
+        handle_add_task(matches, &mut preempt_app),
+        handle_plan(matches, &mut preempt_app),
+        handle_add_context(matches, &mut preempt_app, &mut warnings),
+        handle_remove_context(matches, &mut preempt_app),
+        {
+            handle_list_contexts(matches, &preempt_app);
+            Ok(())
+        },
+        handle_show_context(matches, &mut preempt_app),
+        handle_add_exception(matches, &mut preempt_app),
+        handle_save_preset(matches, &mut preempt_app),
+        handle_timeline(matches, &mut preempt_app, &mut warnings),
+        handle_tui(matches, &preempt_app),
+        {
+            handle_next(matches, &preempt_app);
+            handle_stats(matches, &preempt_app);
+            Ok(())
+        },
+        handle_show_schedule(matches, &preempt_app),
+        handle_lock_schedule(matches, &mut preempt_app),
+        handle_unlock_schedule(matches, &mut preempt_app),
+        handle_diff_schedule(matches, &preempt_app),
+        handle_export_md(matches, &preempt_app),
+        {
+            handle_today(matches, &preempt_app);
+            handle_list_tasks(matches, &preempt_app);
+            Ok(())
+        },
+        handle_show_task(matches, &preempt_app),
+        handle_archive_task(matches, &mut preempt_app),
+        handle_remove_task(matches, &mut preempt_app),
+        handle_snooze_task(matches, &mut preempt_app),
+        handle_reorder_task(matches, &mut preempt_app),
+        handle_deps(matches, &preempt_app),
+        handle_split_task(matches, &mut preempt_app),
+        handle_roll_over(matches, &mut preempt_app),
+        handle_import_csv(matches, &mut preempt_app),
+        handle_import_holidays(matches, &mut preempt_app),
+        handle_purge(matches, &mut preempt_app, location),
+    ];
+
+    // A failed command leaves nothing worth saving.
+    if let Some(failure) = results.into_iter().find_map(Result::err) {
+        eprintln!("{}", failure.message());
+        return failure.exit_code();
     }
-    handle_add_context(&matches, &mut preempt_app);
-    handle_show_context(&matches, &mut preempt_app);
-    handle_timeline(&matches, &preempt_app);
 
-    match save(&preempt_app) {
+    if !warnings.messages.is_empty() {
+        for message in &warnings.messages {
+            eprintln!("{}", message);
+        }
+        eprintln!("Aborting because of warnings in --strict mode; nothing was saved.");
+        return 1;
+    }
+
+    match save(&preempt_app, location, &known_problems) {
         Ok(_) => (),
-        Err(error) => println!("Error saving data: {}", error),
+        Err(error) => {
+            let failure = Failure::Data(format!("Error saving data: {}", error));
+            eprintln!("{}", failure.message());
+            return failure.exit_code();
+        }
     }
 
-    Ok(())
+    0
 }
@@ -1,22 +1,90 @@
 use crate::timeblock::TimeBlock;
 
 /// Various file operations.
+use super::clock;
 use super::context::Context;
-use super::schedule::build_schedule;
-use super::task::Task;
+use super::schedule::{
+    build_schedule, deadline_risks, is_block_for, summarize, worked_on, DeadlineRisk, EnergyCurve,
+    ScheduleOptions, SchedulePreset, ScheduleResult, ScheduleSummary, TaskStanding,
+};
+use super::task::{format_duration, name_key, Task};
 
-use chrono::Utc;
+use chrono::{Duration, NaiveDate, NaiveDateTime, Weekday};
 use directories::ProjectDirs;
 use serde;
 use serde_yaml;
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-#[derive(serde::Serialize, serde::Deserialize)]
+/// Where task names have to be unique.
+#[derive(
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Eq,
+    PartialEq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum UniqueScope {
+    /// No two tasks share a name.
+    #[default]
+    Global,
+    /// No two tasks in the same context share a name, so "Email" can be in both Work and
+    /// Personal. The context a task is listed under first is the one that counts.
+    PerContext,
+}
+
+/// The preempt data file, preempt_data.yaml.
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+#[schemars(extend("$id" = "https://github.com/oberhauserg/preempt/preempt_data.schema.json"))]
 pub struct PreemptApp {
     tasks: Vec<Task>,
     contexts: Vec<Context>,
+    /// The stored scheduling configuration.
+    #[serde(default)]
+    pub schedule_options: ScheduleOptions,
+    /// The first day of the week in weekly views.
+    #[serde(default = "default_week_start")]
+    pub week_start: Weekday,
+    /// Whether priority 0 tasks are scheduled. When off, they're treated as "someday" and only
+    /// listed.
+    #[serde(default = "default_schedule_zero_priority")]
+    pub schedule_zero_priority: bool,
+    /// Where task names have to be unique.
+    #[serde(default)]
+    pub unique_scope: UniqueScope,
+    /// How sharp the user is through the day, by hour. Flat unless configured.
+    #[serde(default)]
+    pub energy_curve: EnergyCurve,
+    /// Schedules kept with `timeline --save`, one per date.
+    #[serde(default)]
+    saved_schedules: Vec<(NaiveDate, Vec<TimeBlock>)>,
+    /// Dates locked with `lock-schedule`, where `timeline` shows the saved schedule instead of
+    /// building a new one.
+    #[serde(default)]
+    locked_dates: Vec<NaiveDate>,
+    /// Named scheduling settings kept with `save-preset`.
+    #[serde(default)]
+    presets: Vec<SchedulePreset>,
+}
+
+fn default_week_start() -> Weekday {
+    Weekday::Mon
+}
+
+fn default_schedule_zero_priority() -> bool {
+    true
+}
+
+impl Default for PreemptApp {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PreemptApp {
@@ -24,55 +92,676 @@ impl PreemptApp {
         PreemptApp {
             tasks: vec![],
             contexts: vec![],
+            schedule_options: ScheduleOptions::default(),
+            week_start: default_week_start(),
+            schedule_zero_priority: default_schedule_zero_priority(),
+            unique_scope: UniqueScope::default(),
+            energy_curve: EnergyCurve::default(),
+            saved_schedules: vec![],
+            locked_dates: vec![],
+            presets: vec![],
         }
     }
 
-    pub fn add_task(&mut self, task: Task) -> Result<(), &'static str> {
-        if self.get_task(&task.name).is_none() {
+    /// Adds a task, returning a reference to the stored copy.
+    pub fn add_task(&mut self, task: Task) -> Result<&Task, &'static str> {
+        if self.find_duplicate(&task).is_none() {
             self.tasks.push(task);
-            return Ok(());
+            Ok(self.tasks.last().unwrap())
         } else {
             Err("Task already exists")
         }
     }
 
-    pub fn get_task(&self, name: &String) -> Option<&Task> {
-        for task in &self.tasks {
-            if task.name.to_lowercase() == name.to_lowercase() {
-                return Some(&task);
+    /// Whether the two tasks can't both be kept under the unique scope.
+    fn is_duplicate(&self, first: &Task, second: &Task) -> bool {
+        name_key(&first.name) == name_key(&second.name)
+            && match self.unique_scope {
+                UniqueScope::Global => true,
+                UniqueScope::PerContext => {
+                    first.context().map(name_key) == second.context().map(name_key)
+                }
             }
+    }
+
+    /// The stored task that `task` would duplicate, if any.
+    pub fn find_duplicate(&self, task: &Task) -> Option<&Task> {
+        self.tasks
+            .iter()
+            .find(|stored| self.is_duplicate(stored, task))
+    }
+
+    /// Like `find_duplicate`, but mutable.
+    pub fn find_duplicate_mut(&mut self, task: &Task) -> Option<&mut Task> {
+        let index = self
+            .tasks
+            .iter()
+            .position(|stored| self.is_duplicate(stored, task))?;
+        self.tasks.get_mut(index)
+    }
+
+    /// Looks a task up by name. With a context, only a task listed under that context first
+    /// matches, which tells apart tasks that share a name under `UniqueScope::PerContext`.
+    pub fn get_task(&self, name: &str, context: Option<&str>) -> Option<&Task> {
+        self.tasks.iter().find(|task| is_named(task, name, context))
+    }
+
+    /// Lists tasks in the order they were added. Archived tasks are left out unless requested.
+    pub fn list_tasks(&self, include_archived: bool) -> Vec<&Task> {
+        self.tasks
+            .iter()
+            .filter(|task| include_archived || !task.is_archived())
+            .collect()
+    }
+
+    /// Tasks on today's plate: open tasks whose context is active today, plus any due today.
+    /// Snoozed tasks are left out.
+    pub fn tasks_due_today(&self, today: NaiveDate) -> Vec<&Task> {
+        self.tasks
+            .iter()
+            .filter(|task| task.is_schedulable() && !task.is_snoozed_on(today))
+            .filter(|task| {
+                let context_active = task
+                    .contexts()
+                    .iter()
+                    .filter_map(|name| self.get_context(name))
+                    .any(|context| !context.get_timeblock(today).is_empty());
+
+                context_active || task.deadline() == Some(today)
+            })
+            .collect()
+    }
+
+    pub fn archive_task(&mut self, name: &str, context: Option<&str>) -> Result<(), &'static str> {
+        match self.get_task_mut(name, context) {
+            Some(task) => {
+                task.archive();
+                Ok(())
+            }
+            None => Err("Task doesn't exist"),
         }
-        None
     }
 
-    pub fn add_context(&mut self, context: Context) -> Result<(), &'static str> {
+    /// Removes a task for good, unlike archiving, which keeps it for history.
+    pub fn remove_task(&mut self, name: &str, context: Option<&str>) -> Result<(), &'static str> {
+        let index = self
+            .tasks
+            .iter()
+            .position(|task| is_named(task, name, context))
+            .ok_or("Task doesn't exist")?;
+        self.tasks.remove(index);
+        Ok(())
+    }
+
+    /// Keeps a task out of schedules until `until`.
+    pub fn snooze_task(
+        &mut self,
+        name: &str,
+        context: Option<&str>,
+        until: NaiveDate,
+    ) -> Result<(), &'static str> {
+        match self.get_task_mut(name, context) {
+            Some(task) => {
+                task.set_snoozed_until(Some(until));
+                Ok(())
+            }
+            None => Err("Task doesn't exist"),
+        }
+    }
+
+    /// Sets or clears a task's manual place in its priority class.
+    pub fn reorder_task(
+        &mut self,
+        name: &str,
+        context: Option<&str>,
+        order: Option<i32>,
+    ) -> Result<(), &'static str> {
+        match self.get_task_mut(name, context) {
+            Some(task) => {
+                task.set_order(order);
+                Ok(())
+            }
+            None => Err("Task doesn't exist"),
+        }
+    }
+
+    /// Replaces a task with `n` smaller pieces, keeping its place in the task list.
+    pub fn split_task(
+        &mut self,
+        name: &str,
+        context: Option<&str>,
+        n: usize,
+    ) -> Result<Vec<&Task>, &'static str> {
+        let index = self
+            .tasks
+            .iter()
+            .position(|task| is_named(task, name, context))
+            .ok_or("Task doesn't exist")?;
+
+        let pieces = self.tasks[index].clone().split(n);
+        if pieces
+            .iter()
+            .any(|piece| self.find_duplicate(piece).is_some())
+        {
+            return Err("A task with one of the split names already exists");
+        }
+
+        let count = pieces.len();
+        self.tasks.splice(index..index + 1, pieces);
+        Ok(self.tasks[index..index + count].iter().collect())
+    }
+
+    /// Renders the task's prerequisites as an indented tree, one line per task. Dependencies that
+    /// lead back to a task already on the current path are marked as cycles instead of expanded.
+    pub fn dependency_tree(&self, name: &str) -> Result<Vec<String>, &'static str> {
+        let task = self.get_task(name, None).ok_or("Task doesn't exist")?;
+        let mut lines = vec![];
+        let mut path = vec![];
+        self.collect_dependencies(task, 0, &mut path, &mut lines);
+        Ok(lines)
+    }
+
+    fn collect_dependencies(
+        &self,
+        task: &Task,
+        depth: usize,
+        path: &mut Vec<String>,
+        lines: &mut Vec<String>,
+    ) {
+        let indent = "  ".repeat(depth);
+        let key = name_key(&task.name);
+
+        if path.contains(&key) {
+            lines.push(format!("{}{} (cycle)", indent, task.name));
+            return;
+        }
+        lines.push(format!("{}{}", indent, task.name));
+
+        path.push(key);
+        for dependency in task.depends_on() {
+            match self.get_task(dependency, None) {
+                Some(dependency) => self.collect_dependencies(dependency, depth + 1, path, lines),
+                None => lines.push(format!("{}  {} (missing)", indent, dependency)),
+            }
+        }
+        path.pop();
+    }
+
+    /// Adds a context. Overlapping an existing context is allowed, but the names of any contexts
+    /// it overlaps are returned so the caller can warn about them.
+    pub fn add_context(&mut self, context: Context) -> Result<Vec<String>, &'static str> {
         if self.get_context(&context.name).is_none() {
+            let overlapping = self
+                .contexts
+                .iter()
+                .filter(|existing| existing.overlaps(&context))
+                .map(|existing| existing.name.clone())
+                .collect();
             self.contexts.push(context);
-            return Ok(());
+            Ok(overlapping)
         } else {
             Err("Context already exists")
         }
     }
 
-    pub fn get_context(&self, name: &String) -> Option<&Context> {
+    pub fn contexts(&self) -> &[Context] {
+        &self.contexts
+    }
+
+    /// How many open tasks belong to the context.
+    pub fn context_task_count(&self, context: &Context) -> usize {
+        self.tasks
+            .iter()
+            .filter(|task| task.is_schedulable() && task.is_context_match(&context.name))
+            .count()
+    }
+
+    pub fn get_context(&self, name: &str) -> Option<&Context> {
+        self.contexts
+            .iter()
+            .find(|context| name_key(&context.name) == name_key(name))
+    }
+
+    pub fn get_task_mut(&mut self, name: &str, context: Option<&str>) -> Option<&mut Task> {
+        self.tasks
+            .iter_mut()
+            .find(|task| is_named(task, name, context))
+    }
+
+    pub fn get_context_mut(&mut self, name: &str) -> Option<&mut Context> {
+        self.contexts
+            .iter_mut()
+            .find(|context| name_key(&context.name) == name_key(name))
+    }
+
+    /// Checks the invariants the rest of the app relies on: task and context names are unique
+    /// (ignoring case), durations aren't negative, and context windows aren't empty. Returns every
+    /// problem found.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = vec![];
+
+        for (i, task) in self.tasks.iter().enumerate() {
+            if let Some(other) = self.tasks[..i]
+                .iter()
+                .find(|other| self.is_duplicate(other, task))
+            {
+                problems.push(duplicate_problem("task", &other.name, &task.name));
+            }
+            if task.duration() < Duration::zero() {
+                problems.push(format!("Task '{}' has a negative duration", task.name));
+            }
+        }
+
+        for (i, context) in self.contexts.iter().enumerate() {
+            if let Some(other) = self.contexts[..i]
+                .iter()
+                .find(|other| name_key(&other.name) == name_key(&context.name))
+            {
+                problems.push(duplicate_problem("context", &other.name, &context.name));
+            }
+            if context.start == context.end {
+                problems.push(format!(
+                    "Context '{}' has an empty window ({} to {})",
+                    context.name,
+                    context.start.format("%H:%M"),
+                    context.end.format("%H:%M")
+                ));
+            }
+            if context.transition < Duration::zero() {
+                problems.push(format!(
+                    "Context '{}' has a negative transition time",
+                    context.name
+                ));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
+    /// Every problem `validate` finds, plus tasks whose context no longer exists. Orphaned tasks
+    /// don't stop the data being saved, since removing a context leaves them behind on purpose.
+    pub fn check(&self) -> Vec<String> {
+        let mut problems = self.validate().err().unwrap_or_default();
         for context in &self.contexts {
-            if context.name.to_lowercase() == name.to_lowercase() {
-                return Some(&context);
+            problems.extend(self.short_window_warnings(context));
+        }
+        for task in &self.tasks {
+            for context in task.contexts() {
+                if self.get_context(context).is_none() && task.is_schedulable() {
+                    problems.push(format!(
+                        "Task '{}' refers to the missing context '{}'",
+                        task.name, context
+                    ));
+                }
             }
         }
-        None
+        problems
     }
 
-    pub fn build_schedule(&self) -> Vec<TimeBlock> {
+    /// Explains each of the context's windows that's too short for one work block, so its tasks
+    /// would never be scheduled there. Focus contexts don't use work blocks and are never short.
+    pub fn short_window_warnings(&self, context: &Context) -> Vec<String> {
+        if context.focus_mode {
+            return vec![];
+        }
+        let work = self.schedule_options.pomodoro.work();
+        context
+            .short_windows(work)
+            .into_iter()
+            .map(|window| {
+                format!(
+                    "Context '{}' has a window ({}) shorter than one {} work block",
+                    context.name,
+                    window,
+                    format_duration(work)
+                )
+            })
+            .collect()
+    }
+
+    /// Removes a context. Tasks that referred to it are kept, and show up as orphaned until they
+    /// are moved to another context.
+    pub fn remove_context(&mut self, name: &str) -> Result<(), &'static str> {
+        let count = self.contexts.len();
+        self.contexts
+            .retain(|context| name_key(&context.name) != name_key(name));
+        if self.contexts.len() == count {
+            return Err("Context doesn't exist.");
+        }
+        Ok(())
+    }
+
+    pub fn build_schedule(&self, options: &ScheduleOptions) -> Vec<TimeBlock> {
+        self.build_schedule_for(clock::today(), options)
+    }
+
+    pub fn build_schedule_for(&self, date: NaiveDate, options: &ScheduleOptions) -> Vec<TimeBlock> {
+        self.schedule_for(date, options).blocks
+    }
+
+    /// Builds the schedule for a date, along with the tasks that didn't get any time.
+    pub fn schedule_for(&self, date: NaiveDate, options: &ScheduleOptions) -> ScheduleResult {
+        self.schedule_tasks(&self.tasks, date, options)
+    }
+
+    /// Builds the schedule for `date` from the given tasks rather than the stored ones.
+    fn schedule_tasks(
+        &self,
+        tasks: &[Task],
+        date: NaiveDate,
+        options: &ScheduleOptions,
+    ) -> ScheduleResult {
+        let mut options = options.clone();
+        options.energy_curve = self.energy_curve.clone();
+        let tasks: Vec<Task> = tasks
+            .iter()
+            .filter(|task| self.is_planned(task))
+            .filter(|task| !task.is_snoozed_on(date))
+            .cloned()
+            .collect();
+
         build_schedule(
             &self.contexts,
-            &self.tasks,
+            &tasks,
             TimeBlock::new(
                 chrono::NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
                 chrono::NaiveTime::from_hms_opt(23, 59, 59).unwrap(),
-                Utc::now().date_naive(),
-                Utc::now().date_naive(),
+                date,
+                date,
             ),
+            &options,
+        )
+    }
+
+    /// Whether schedules include the task at all. Priority 0 tasks are left out when they aren't
+    /// being scheduled.
+    fn is_planned(&self, task: &Task) -> bool {
+        self.schedule_zero_priority || task.priority != 0
+    }
+
+    /// Plans one day after another from `start`, carrying unfinished work forward, until every
+    /// task has had its time or `max_days` days are planned. Returns each day's schedule along
+    /// with the work still left at the end, by task.
+    ///
+    /// A task that nothing will ever schedule, like one whose context is never active, keeps
+    /// the plan going until the cap and is reported as left over.
+    pub fn plan_until_done(
+        &self,
+        start: NaiveDate,
+        max_days: usize,
+        options: &ScheduleOptions,
+    ) -> (Vec<ScheduleResult>, Vec<(String, Duration)>) {
+        let mut options = options.clone();
+        let mut tasks = self.tasks.clone();
+        let open = |tasks: &[Task]| -> Vec<(String, Duration)> {
+            tasks
+                .iter()
+                .filter(|task| task.is_schedulable() && self.is_planned(task))
+                .filter(|task| task.has_work_remaining())
+                .map(|task| (task.name.clone(), task.duration()))
+                .collect()
+        };
+
+        let mut results = vec![];
+        for date in start.iter_days().take(max_days) {
+            if open(&tasks).is_empty() {
+                break;
+            }
+            let mut result = self.schedule_tasks(&tasks, date, &options);
+            if let Some(locked) = self.locked_schedule(date) {
+                result.blocks = locked.to_vec();
+            }
+            options.low_turns_before += result.low_turns;
+            for task in &mut tasks {
+                task.do_work(worked_on(&result.blocks, task));
+                if !task.has_work_remaining() {
+                    task.complete();
+                }
+            }
+            results.push(result);
+        }
+        (results, open(&tasks))
+    }
+
+    /// Builds the schedules for several days in a row. The Low class round-robin carries on from
+    /// one day to the next, so chores take turns over the run.
+    pub fn schedule_days(
+        &self,
+        dates: &[NaiveDate],
+        options: &ScheduleOptions,
+    ) -> Vec<ScheduleResult> {
+        let mut options = options.clone();
+        dates
+            .iter()
+            .map(|date| {
+                let result = self.schedule_for(*date, &options);
+                options.low_turns_before += result.low_turns;
+                result
+            })
+            .collect()
+    }
+
+    /// Resets the tasks whose reset schedule has come round, returning their names.
+    pub fn reset_tasks(&mut self, now: NaiveDateTime) -> Vec<String> {
+        self.tasks
+            .iter_mut()
+            .filter_map(|task| task.reset_if_due(now).then(|| task.name.clone()))
+            .collect()
+    }
+
+    /// Where each open task stands in `result`, the schedule built for `date` with `options`.
+    pub fn explain_schedule(
+        &self,
+        date: NaiveDate,
+        options: &ScheduleOptions,
+        result: &ScheduleResult,
+    ) -> Vec<(String, TaskStanding)> {
+        self.tasks
+            .iter()
+            .filter(|task| task.is_schedulable())
+            .map(|task| {
+                let worked = worked_on(&result.blocks, task);
+                let unscheduled = result.unscheduled.iter().find(|unscheduled| {
+                    unscheduled.name == task.name
+                        && unscheduled.context.as_deref() == task.context()
+                });
+                let standing = if task.is_snoozed_on(date) {
+                    TaskStanding::Snoozed(task.snoozed_until().unwrap()) // safe to unwrap because it's snoozed
+                } else if !self.schedule_zero_priority && task.priority == 0 {
+                    TaskStanding::ZeroPriority
+                } else if worked >= task.duration() {
+                    TaskStanding::Scheduled(worked)
+                } else if !worked.is_zero() {
+                    TaskStanding::Deferred {
+                        worked,
+                        left: task.duration() - worked,
+                    }
+                } else if self.locked_dates.contains(&date) {
+                    TaskStanding::LeftOutOfLock
+                } else if let Some(unscheduled) = unscheduled {
+                    TaskStanding::Unscheduled(unscheduled.reason.clone())
+                } else if task.contexts().is_empty() {
+                    TaskStanding::NoContext
+                } else {
+                    let (excluded, inactive): (Vec<&Context>, Vec<&Context>) = self
+                        .contexts
+                        .iter()
+                        .filter(|context| task.is_context_match(&context.name))
+                        .partition(|context| !context.get_timeblock(date).is_empty());
+                    let names = |contexts: Vec<&Context>| {
+                        contexts
+                            .iter()
+                            .map(|context| context.name.clone())
+                            .collect()
+                    };
+                    // A context with a window that day only gives nothing when `--context` left
+                    // it out.
+                    if options.only_contexts.is_some() && !excluded.is_empty() {
+                        TaskStanding::Excluded(names(excluded))
+                    } else {
+                        TaskStanding::Inactive(names(inactive))
+                    }
+                };
+                (task.name.clone(), standing)
+            })
+            .collect()
+    }
+
+    /// The tasks that the given schedule for `date` leaves short of their deadlines.
+    pub fn deadline_risks(&self, date: NaiveDate, schedule: &[TimeBlock]) -> Vec<DeadlineRisk> {
+        deadline_risks(&self.tasks, schedule, date)
+    }
+
+    /// The day of the seven starting at `week_start` with the most scheduled work, and how much
+    /// work that is. Ties go to the earlier day.
+    pub fn busiest_day(&self, week_start: NaiveDate) -> (NaiveDate, Duration) {
+        (0..7)
+            .map(|offset| week_start + Duration::days(offset))
+            .map(|date| (date, self.work_on(date)))
+            .fold((week_start, Duration::zero()), |busiest, day| {
+                if day.1 > busiest.1 {
+                    day
+                } else {
+                    busiest
+                }
+            })
+    }
+
+    /// The totals of a schedule, with the estimate uncertainty of the tasks in it.
+    pub fn summarize(&self, schedule: &[TimeBlock]) -> ScheduleSummary {
+        summarize(schedule).with_uncertainty(schedule, &self.tasks)
+    }
+
+    /// The work and break totals of the schedule for `date`.
+    pub fn summary_on(&self, date: NaiveDate) -> ScheduleSummary {
+        self.summarize(&self.build_schedule_for(date, &self.schedule_options))
+    }
+
+    /// The total length of the work blocks scheduled on `date`.
+    pub fn work_on(&self, date: NaiveDate) -> Duration {
+        self.summary_on(date).work
+    }
+
+    /// Keeps a generated schedule for later review, replacing any saved for the same date.
+    pub fn save_schedule(&mut self, date: NaiveDate, blocks: Vec<TimeBlock>) {
+        self.saved_schedules
+            .retain(|(saved_date, _)| *saved_date != date);
+        self.saved_schedules.push((date, blocks));
+        self.saved_schedules
+            .sort_by_key(|(saved_date, _)| *saved_date);
+    }
+
+    pub fn saved_schedule(&self, date: NaiveDate) -> Option<&[TimeBlock]> {
+        self.saved_schedules
+            .iter()
+            .find(|(saved_date, _)| *saved_date == date)
+            .map(|(_, blocks)| blocks.as_slice())
+    }
+
+    /// Saves the schedule for `date` and locks it, so `timeline` keeps showing it until it's
+    /// unlocked.
+    pub fn lock_schedule(
+        &mut self,
+        date: NaiveDate,
+        blocks: Vec<TimeBlock>,
+    ) -> Result<(), &'static str> {
+        if self.locked_dates.contains(&date) {
+            return Err("The schedule is already locked");
+        }
+        self.save_schedule(date, blocks);
+        self.locked_dates.push(date);
+        self.locked_dates.sort();
+        Ok(())
+    }
+
+    /// Unlocks the schedule for `date`. The saved schedule is kept.
+    pub fn unlock_schedule(&mut self, date: NaiveDate) -> Result<(), &'static str> {
+        if !self.locked_dates.contains(&date) {
+            return Err("The schedule isn't locked");
+        }
+        self.locked_dates.retain(|locked| *locked != date);
+        Ok(())
+    }
+
+    /// The saved schedule for `date`, if it's locked.
+    pub fn locked_schedule(&self, date: NaiveDate) -> Option<&[TimeBlock]> {
+        if self.locked_dates.contains(&date) {
+            self.saved_schedule(date)
+        } else {
+            None
+        }
+    }
+
+    /// Stores a preset, replacing any existing one with the same name.
+    pub fn save_preset(&mut self, preset: SchedulePreset) {
+        self.presets.retain(|saved| saved.name != preset.name);
+        self.presets.push(preset);
+    }
+
+    pub fn get_preset(&self, name: &str) -> Option<&SchedulePreset> {
+        self.presets.iter().find(|preset| preset.name == name)
+    }
+
+    /// Rolls unfinished work forward. The schedule for `from` is simulated, the work it planned is
+    /// applied to each task, and whatever remains is snoozed until `to`, so it's next scheduled
+    /// then. A task already snoozed past `to` stays snoozed. Tasks with no work left are marked
+    /// done. Returns the names of tasks that still have work.
+    pub fn reschedule_incomplete(
+        &mut self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<String>, &'static str> {
+        if to <= from {
+            return Err("Can only roll work forward to a later date");
+        }
+
+        let schedule = self.build_schedule_for(from, &self.schedule_options.clone());
+        let mut carried = vec![];
+
+        for task in &mut self.tasks {
+            let worked = schedule
+                .iter()
+                .filter(|block| is_block_for(block, task))
+                .fold(Duration::zero(), |total, block| total + block.duration());
+
+            if worked == Duration::zero() {
+                continue;
+            }
+
+            task.do_work(worked);
+            if task.has_work_remaining() {
+                if task.snoozed_until().is_none_or(|until| until < to) {
+                    task.set_snoozed_until(Some(to));
+                }
+                carried.push(task.name.clone());
+            } else {
+                task.complete();
+            }
+        }
+
+        Ok(carried)
+    }
+}
+
+/// Whether the task has the name, and when a context is given, is listed under it first.
+fn is_named(task: &Task, name: &str, context: Option<&str>) -> bool {
+    name_key(&task.name) == name_key(name)
+        && context.is_none_or(|context| task.context().map(name_key) == Some(name_key(context)))
+}
+
+/// Describes two names that collide. Lookups ignore case, so names differing only by case can't
+/// both be reached.
+fn duplicate_problem(kind: &str, first: &str, second: &str) -> String {
+    if first == second {
+        format!("Duplicate {} name '{}'", kind, second)
+    } else {
+        format!(
+            "Duplicate {} names '{}' and '{}' differ only by case",
+            kind, first, second
         )
     }
 }
@@ -81,49 +770,209 @@ pub fn get_dir() -> Option<ProjectDirs> {
     ProjectDirs::from("com", "grant", "preempt")
 }
 
-pub fn save(data: &PreemptApp) -> Result<(), &'static str> {
-    let serialized_data = serde_yaml::to_string(data).unwrap();
+const DATA_FILE_NAME: &str = "preempt_data.yaml";
 
-    if let Some(proj_dirs) = get_dir() {
-        let data_dir = proj_dirs.data_dir();
-        std::fs::create_dir_all(&data_dir).map_err(|_| "Couldn't create directory")?;
+/// Where the data file lives. Both parts are optional: the directory defaults to the platform data
+/// directory and the profile to the default data file.
+#[derive(Clone, Debug, Default)]
+pub struct DataLocation {
+    pub dir: Option<PathBuf>,
+    /// A named dataset, kept in `preempt_<profile>.yaml` alongside the default one.
+    pub profile: Option<String>,
+}
 
-        let file_path = data_dir.join("preempt_data.yaml"); // specify the file name
-        let path = Path::new(&file_path);
+impl DataLocation {
+    pub fn new(dir: Option<PathBuf>, profile: Option<String>) -> DataLocation {
+        DataLocation { dir, profile }
+    }
 
-        let mut file = match OpenOptions::new().write(true).create(true).open(&path) {
-            Err(_) => return Err("Couldn't open file"),
-            Ok(file) => file,
-        };
+    /// The directory holding the data file. An explicit directory wins over the platform default,
+    /// and the current directory is used when the platform has none.
+    pub fn dir(&self) -> PathBuf {
+        match &self.dir {
+            Some(dir) => dir.clone(),
+            None => get_dir()
+                .map(|proj_dirs| proj_dirs.data_dir().to_path_buf())
+                .unwrap_or_else(|| PathBuf::from(".")),
+        }
+    }
+
+    /// Whether the data file falls back to the current directory because no directory was given
+    /// and the platform doesn't have a data directory.
+    pub fn is_fallback(&self) -> bool {
+        self.dir.is_none() && get_dir().is_none()
+    }
 
-        match file.write_all(serialized_data.as_bytes()) {
-            Err(_) => return Err("Couldn't write to file"),
-            Ok(_) => (),
+    pub fn file_name(&self) -> String {
+        match &self.profile {
+            Some(profile) => format!("preempt_{}.yaml", profile),
+            None => DATA_FILE_NAME.to_string(),
         }
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.dir().join(self.file_name())
+    }
+}
+
+/// Checks a profile name is safe to put in a file name.
+pub fn validate_profile(profile: &str) -> Result<(), String> {
+    if !profile.is_empty()
+        && profile
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        Ok(())
     } else {
-        return Err("Couldn't get directory");
+        Err(String::from(
+            "Profile names may only contain letters, digits, '-' and '_'",
+        ))
     }
+}
 
-    Ok(())
+/// How many times to try for the lock before giving up, doubling the wait each time.
+const LOCK_ATTEMPTS: u32 = 5;
+
+/// A lock file next to the data file, held from load to save so two instances can't overwrite
+/// each other's changes. It's removed when dropped.
+pub struct DataLock {
+    path: Option<PathBuf>,
 }
 
-pub fn load() -> Result<PreemptApp, Box<dyn std::error::Error>> {
-    if let Some(proj_dirs) = get_dir() {
-        let data_dir = proj_dirs.data_dir();
-        let file_path = data_dir.join("preempt_data.yaml");
-        let path = Path::new(&file_path);
-        let mut file = File::open(&path)?;
+impl DataLock {
+    /// Takes the lock, waiting briefly while another instance holds it. When the lock file can't
+    /// be created for any other reason, such as a read-only data directory, the command goes ahead
+    /// unlocked and saving reports the real problem.
+    pub fn acquire(location: &DataLocation) -> Result<DataLock, String> {
+        let path = location.path().with_extension("lock");
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
 
-        let mut serialized_data = String::new();
-        file.read_to_string(&mut serialized_data)?;
+        let mut delay = std::time::Duration::from_millis(50);
+        for attempt in 1..=LOCK_ATTEMPTS {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    let _ = write!(file, "{}", std::process::id());
+                    return Ok(DataLock { path: Some(path) });
+                }
+                Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => {
+                    if attempt < LOCK_ATTEMPTS {
+                        std::thread::sleep(delay);
+                        delay *= 2;
+                    }
+                }
+                Err(_) => return Ok(DataLock { path: None }),
+            }
+        }
+        Err(format!(
+            "Another preempt instance is running. If it isn't, remove {}.",
+            path.display()
+        ))
+    }
+}
 
-        let deserialized_data: PreemptApp = serde_yaml::from_str(&serialized_data)?;
+impl Drop for DataLock {
+    fn drop(&mut self) {
+        if let Some(path) = &self.path {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Copies the data file next to itself with a timestamped name, returning the copy's path. Returns
+/// `None` when there's no data file to back up.
+pub fn backup(location: &DataLocation) -> Result<Option<PathBuf>, String> {
+    let path = location.path();
+    if !path.exists() {
+        return Ok(None);
+    }
 
-        Ok(deserialized_data)
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let backup_path = path.with_file_name(format!(
+        "{}.{}.bak.yaml",
+        stem,
+        clock::now().format("%Y%m%dT%H%M%S")
+    ));
+    std::fs::copy(&path, &backup_path)
+        .map_err(|error| format!("Couldn't back up {}: {}", path.display(), error))?;
+    Ok(Some(backup_path))
+}
+
+/// Describes a failure to save. Permission problems get their own message pointing at the data
+/// directory overrides, since retrying won't help.
+fn save_error(message: &str, path: &Path, error: std::io::Error) -> String {
+    if error.kind() == std::io::ErrorKind::PermissionDenied {
+        format!(
+            "Permission denied writing {}. Use --data-dir or PREEMPT_DATA_DIR to save somewhere writable.",
+            path.display()
+        )
     } else {
-        Err(Box::new(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            "Couldn't get directory",
-        )))
+        format!("{} {}: {}", message, path.display(), error)
+    }
+}
+
+/// Writes the app to its data file. An app with validation problems beyond `known_problems` is
+/// never written, so a bad edit can't corrupt the stored data. Passing the problems the data had
+/// when it was loaded keeps an already invalid file saveable, so it can be repaired.
+pub fn save(
+    data: &PreemptApp,
+    location: &DataLocation,
+    known_problems: &[String],
+) -> Result<(), String> {
+    if let Err(problems) = data.validate() {
+        let new_problems: Vec<&str> = problems
+            .iter()
+            .filter(|problem| !known_problems.contains(problem))
+            .map(String::as_str)
+            .collect();
+        if !new_problems.is_empty() {
+            return Err(format!(
+                "Refusing to save invalid data:\n  - {}",
+                new_problems.join("\n  - ")
+            ));
+        }
+    }
+
+    let serialized_data = serde_yaml::to_string(data).unwrap();
+
+    let data_dir = location.dir();
+    std::fs::create_dir_all(&data_dir)
+        .map_err(|error| save_error("Couldn't create directory", &data_dir, error))?;
+
+    let file_path = data_dir.join(location.file_name());
+    let path = Path::new(&file_path);
+
+    let mut file = match OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+    {
+        Err(error) => return Err(save_error("Couldn't open file", path, error)),
+        Ok(file) => file,
+    };
+
+    if let Err(error) = file.write_all(serialized_data.as_bytes()) {
+        return Err(save_error("Couldn't write to file", path, error));
     }
+
+    Ok(())
+}
+
+pub fn load(location: &DataLocation) -> Result<PreemptApp, Box<dyn std::error::Error>> {
+    let file_path = location.path();
+    let path = Path::new(&file_path);
+    let mut file = File::open(path)?;
+
+    let mut serialized_data = String::new();
+    file.read_to_string(&mut serialized_data)?;
+
+    let mut deserialized_data: PreemptApp = serde_yaml::from_str(&serialized_data)?;
+    deserialized_data.reset_tasks(clock::now());
+
+    Ok(deserialized_data)
 }
@@ -0,0 +1,108 @@
+/// Serde adapters that keep the data file easy to edit by hand.
+///
+/// Times are written as "09:00" and durations as minutes, like "25m". Files written before these
+/// adapters, with times as "09:00:00" and durations as a number of seconds, still load.
+use std::borrow::Cow;
+
+use chrono::{Duration, NaiveTime, Timelike};
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::{de, Deserialize, Deserializer, Serializer};
+use serde_with::{DeserializeAs, SerializeAs};
+
+/// A duration written as whole minutes with an "m" suffix. Durations that aren't a whole number
+/// of minutes are written as plain seconds, so nothing is lost.
+pub struct Minutes;
+
+/// A time of day written as "HH:MM", with seconds only when there are some.
+pub struct ClockTime;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredDuration {
+    Seconds(i64),
+    Text(String),
+}
+
+/// Parses "25m", or a plain number of seconds like "1500".
+fn parse_minutes(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    match text.strip_suffix('m') {
+        Some(minutes) => minutes.trim().parse::<i64>().ok().map(Duration::minutes),
+        None => text.parse::<i64>().ok().map(Duration::seconds),
+    }
+}
+
+impl SerializeAs<Duration> for Minutes {
+    fn serialize_as<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        let seconds = duration.num_seconds();
+        if seconds % 60 == 0 {
+            serializer.serialize_str(&format!("{}m", seconds / 60))
+        } else {
+            serializer.serialize_i64(seconds)
+        }
+    }
+}
+
+impl<'de> DeserializeAs<'de, Duration> for Minutes {
+    fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        match StoredDuration::deserialize(deserializer)? {
+            StoredDuration::Seconds(seconds) => Ok(Duration::seconds(seconds)),
+            StoredDuration::Text(text) => parse_minutes(&text).ok_or_else(|| {
+                de::Error::custom(format!(
+                    "invalid duration '{}', expected minutes like '25m'",
+                    text
+                ))
+            }),
+        }
+    }
+}
+
+impl SerializeAs<NaiveTime> for ClockTime {
+    fn serialize_as<S: Serializer>(time: &NaiveTime, serializer: S) -> Result<S::Ok, S::Error> {
+        let format = if time.second() == 0 && time.nanosecond() == 0 {
+            "%H:%M"
+        } else {
+            "%H:%M:%S%.f"
+        };
+        serializer.serialize_str(&time.format(format).to_string())
+    }
+}
+
+impl<'de> DeserializeAs<'de, NaiveTime> for ClockTime {
+    fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<NaiveTime, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        NaiveTime::parse_from_str(&text, "%H:%M:%S%.f")
+            .or_else(|_| NaiveTime::parse_from_str(&text, "%H:%M"))
+            .map_err(|_| de::Error::custom(format!("invalid time '{}', expected HH:MM", text)))
+    }
+}
+
+impl JsonSchema for Minutes {
+    fn schema_name() -> Cow<'static, str> {
+        "Minutes".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "Minutes like \"25m\", or a number of seconds.",
+            "oneOf": [
+                { "type": "string", "pattern": "^\\s*-?\\d+\\s*m$" },
+                { "type": "integer" }
+            ]
+        })
+    }
+}
+
+impl JsonSchema for ClockTime {
+    fn schema_name() -> Cow<'static, str> {
+        "ClockTime".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "A time of day like \"09:00\", with seconds when there are some.",
+            "type": "string",
+            "pattern": "^\\d{2}:\\d{2}(:\\d{2}(\\.\\d+)?)?$"
+        })
+    }
+}
@@ -1,76 +1,533 @@
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 
-use chrono::{Duration, NaiveDate, NaiveTime};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Weekday};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
 
 /// Various forms of scheduling.
 use super::context::Context;
-use super::task::Task;
+use super::readable::Minutes;
+use super::task::{format_duration, format_estimate, name_key, DeadlineKind, Difficulty, Task};
 use super::timeblock::TimeBlock;
 
+/// How much an aged task's priority is raised for a scheduling run.
+const AGING_PRIORITY_BOOST: i32 = 3;
+
+/// The algorithm used to order tasks.
+#[derive(Serialize, Deserialize, JsonSchema, Default, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SchedulingStrategy {
+    /// Multilevel queues split into High, Medium, and Low priority classes.
+    #[default]
+    Multilevel,
+    /// A single queue ordered by a continuous score of priority and remaining duration.
+    WeightedScore,
+}
+
+/// How rests between work blocks show up in a schedule.
+#[derive(Serialize, Deserialize, JsonSchema, Default, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakMode {
+    /// Each rest is its own "Break" block.
+    #[default]
+    Explicit,
+    /// Rests are left as gaps, so only work blocks appear.
+    Free,
+}
+
+/// Knobs that change how a schedule is built. The defaults match the standard scheduler.
+///
+/// These are persisted with the app data, so they act as configuration. Command line flags
+/// override the stored values for a single run.
+#[serde_as]
+#[derive(Serialize, Deserialize, JsonSchema, Default, Clone)]
+#[serde(default)]
+pub struct ScheduleOptions {
+    /// Shuffle the low priority queue once per day, so the same chore doesn't always come first.
+    pub shuffle_low: bool,
+    /// Tasks created at least this many days before the scheduled day get a temporary priority
+    /// boost. Aging is disabled when unset.
+    pub aging_threshold_days: Option<i64>,
+    pub strategy: SchedulingStrategy,
+    pub breaks: BreakMode,
+    /// Give Low class tasks round-robin turns in proportion to their priority, rather than equally.
+    pub weighted_low: bool,
+    /// Go straight from the last High or Medium class block to the first Low class one, without
+    /// a short break in between.
+    pub skip_transition_break: bool,
+    /// Copied from the app's energy curve for each run.
+    #[serde(skip)]
+    pub energy_curve: EnergyCurve,
+    /// Block start times are rounded up to a multiple of this, counted from midnight.
+    #[serde_as(as = "Option<Minutes>")]
+    #[schemars(with = "Option<Minutes>")]
+    pub align_to: Option<Duration>,
+    /// The most work blocks in a row one task gets before the next task in its queue has a turn.
+    /// Unlimited when unset.
+    pub max_consecutive_blocks: Option<usize>,
+    /// Loaded from the config file rather than stored with the data.
+    #[serde(skip)]
+    pub pomodoro: PomodoroConfig,
+    /// Only the contexts with these exact names are scheduled. Every context is when unset. Set
+    /// per run.
+    #[serde(skip)]
+    pub only_contexts: Option<Vec<String>>,
+    /// Active contexts with no tasks to schedule get their windows as "Free: <context>" blocks.
+    /// Set per run.
+    #[serde(skip)]
+    pub show_free: bool,
+    /// Low class turns taken on earlier days of a multi-day run. The round-robin starts this far
+    /// along, so the same chore doesn't always go first. Set per run.
+    #[serde(skip)]
+    pub low_turns_before: usize,
+}
+
+/// Energy weights from 0.0 to 1.0 by hour of day. Hours without a weight are neutral.
+///
+/// An hour is low energy when its weight is below the midpoint of the lowest and highest weights
+/// given, so only the shape of the curve matters. An empty or flat curve changes nothing.
+#[derive(Serialize, Deserialize, JsonSchema, Default, Clone, Debug, PartialEq)]
+#[serde(transparent)]
+pub struct EnergyCurve(pub BTreeMap<u32, f64>);
+
+impl EnergyCurve {
+    fn midpoint(&self) -> f64 {
+        let weights = self.0.values().copied();
+        let lowest = weights.clone().fold(f64::INFINITY, f64::min);
+        let highest = weights.fold(f64::NEG_INFINITY, f64::max);
+        (lowest + highest) / 2.0
+    }
+
+    pub fn is_low_at(&self, time: NaiveTime) -> bool {
+        let midpoint = self.midpoint();
+        self.0
+            .get(&time.hour())
+            .is_some_and(|weight| *weight < midpoint)
+    }
+
+    /// An hour is high energy when its weight is above the midpoint of the curve.
+    pub fn is_high_at(&self, time: NaiveTime) -> bool {
+        let midpoint = self.midpoint();
+        self.0
+            .get(&time.hour())
+            .is_some_and(|weight| *weight > midpoint)
+    }
+}
+
+/// A named bundle of scheduling settings, saved with `save-preset` and applied with
+/// `timeline --preset`. The pomodoro cycle is kept beside the options because they don't
+/// persist it themselves.
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+pub struct SchedulePreset {
+    pub name: String,
+    pub options: ScheduleOptions,
+    #[serde(default)]
+    pub pomodoro: PomodoroConfig,
+}
+
+/// The shape of a pomodoro cycle. Lengths are in minutes.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct PomodoroConfig {
+    pub work_minutes: i64,
+    pub short_break_minutes: i64,
+    pub long_break_minutes: i64,
+    /// A long break replaces the short one after every this many work blocks.
+    pub long_break_every: i64,
+}
+
+impl Default for PomodoroConfig {
+    fn default() -> PomodoroConfig {
+        PomodoroConfig {
+            work_minutes: 25,
+            short_break_minutes: 5,
+            long_break_minutes: 20,
+            long_break_every: 4,
+        }
+    }
+}
+
+impl PomodoroConfig {
+    /// Checks every length and the long break cadence are positive.
+    pub fn validate(&self) -> Result<(), String> {
+        let fields = [
+            ("work_minutes", self.work_minutes),
+            ("short_break_minutes", self.short_break_minutes),
+            ("long_break_minutes", self.long_break_minutes),
+            ("long_break_every", self.long_break_every),
+        ];
+        match fields.iter().find(|(_, value)| *value <= 0) {
+            Some((field, value)) => Err(format!(
+                "pomodoro.{} must be positive, got {}",
+                field, value
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether the break after the `count`th work block is a long one.
+    pub fn is_long_break(&self, count: i64) -> bool {
+        count % self.long_break_every.max(1) == 0
+    }
+
+    pub fn work(&self) -> Duration {
+        Duration::minutes(self.work_minutes)
+    }
+
+    pub fn short_break(&self) -> Duration {
+        Duration::minutes(self.short_break_minutes)
+    }
+
+    pub fn long_break(&self) -> Duration {
+        Duration::minutes(self.long_break_minutes)
+    }
+}
+
+/// Raises the priority of tasks that have been waiting longer than the aging threshold and moves
+/// them ahead of the rest. Only the copies used for this scheduling run are changed.
+fn apply_aging(tasks: &[Task], at: NaiveDateTime, options: &ScheduleOptions) -> Vec<Task> {
+    let threshold = match options.aging_threshold_days {
+        Some(days) => Duration::days(days),
+        None => return tasks.to_vec(),
+    };
+
+    let (mut aged, fresh): (Vec<Task>, Vec<Task>) = tasks
+        .iter()
+        .cloned()
+        .partition(|task| task.age_at(at) >= threshold);
+
+    for task in &mut aged {
+        task.priority = (task.priority + AGING_PRIORITY_BOOST).min(10);
+    }
+
+    aged.extend(fresh);
+    aged
+}
+
 /// The priority class provides a simple way to implement Multilevel Queue Scheduling.
-#[derive(Eq, PartialEq)]
-enum PriorityClass {
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PriorityClass {
     High = 2,
     Medium = 1,
     Low = 0,
 }
 
-fn get_task_priority(task: &Task) -> PriorityClass {
-    if task.priority >= 10 {
+impl std::fmt::Display for PriorityClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PriorityClass::High => write!(f, "High"),
+            PriorityClass::Medium => write!(f, "Medium"),
+            PriorityClass::Low => write!(f, "Low"),
+        }
+    }
+}
+
+/// The class a task is scheduled in: 7 and up is High, 3 to 6 is Medium, and the rest is Low.
+pub fn get_task_priority(task: &Task) -> PriorityClass {
+    if task.priority >= 7 {
         PriorityClass::High
-    } else if task.priority <= 6 && task.priority >= 3 {
+    } else if task.priority >= 3 {
         PriorityClass::Medium
     } else {
         PriorityClass::Low
     }
 }
 
-fn get_priority_queue(tasks: &Vec<Task>, class: PriorityClass) -> VecDeque<Task> {
-    let mut queue: VecDeque<Task> = VecDeque::new();
+/// The tasks of one class, queued so `pop_back` takes them in order. Tasks with a manual order go
+/// first, lowest order first, and the rest follow in the order they were added.
+fn get_priority_queue(tasks: &[Task], class: PriorityClass) -> VecDeque<Task> {
+    let mut class_tasks: Vec<&Task> = tasks
+        .iter()
+        .filter(|task| get_task_priority(task) == class)
+        .collect();
+    class_tasks.sort_by_key(|task| (task.order().is_none(), task.order()));
 
-    for task in tasks {
-        if get_task_priority(task) == class {
-            queue.push_front(task.clone());
-        }
+    let mut queue: VecDeque<Task> = VecDeque::new();
+    for task in class_tasks {
+        queue.push_front(task.clone());
     }
 
     queue
 }
 
-/// Creates
-///
-fn create_pomodoro_block(task: &Task, start_time: NaiveTime, date: NaiveDate) -> TimeBlock {
+/// The weighted score of a task. Priority dominates, and shorter remaining work nudges a task
+/// ahead of others with the same priority.
+fn weighted_score(task: &Task) -> f64 {
+    let minutes = task.duration().num_minutes().max(1) as f64;
+    task.priority as f64 + 25.0 / minutes
+}
+
+/// Sorts the queue so the highest scoring task is popped first. Ties keep their existing order.
+fn sort_by_weighted_score(queue: &mut VecDeque<Task>) {
+    queue
+        .make_contiguous()
+        .sort_by(|a, b| weighted_score(a).total_cmp(&weighted_score(b)));
+}
+
+fn get_weighted_queue(tasks: &[Task]) -> VecDeque<Task> {
+    let mut queue: VecDeque<Task> = tasks.iter().rev().cloned().collect();
+    sort_by_weighted_score(&mut queue);
+    queue
+}
+
+/// Moves the low priority task whose turn it is to the back of the queue, where `handle_task`
+/// takes it from. Turns are shared out by smooth weighted round-robin, so a task gets turns in
+/// proportion to its priority plus one, spread out rather than bunched together.
+fn select_weighted_low(queue: &mut VecDeque<Task>, credits: &mut HashMap<String, i64>) {
+    if queue.len() < 2 {
+        return;
+    }
+
+    let weight = |task: &Task| task.priority.max(0) as i64 + 1;
+    let total: i64 = queue.iter().map(weight).sum();
+    for task in queue.iter() {
+        *credits.entry(task.name.clone()).or_insert(0) += weight(task);
+    }
+
+    // On a tie the task nearest the back wins, keeping the plain round-robin order.
+    let (index, _) = queue
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, task)| credits[&task.name])
+        .unwrap(); // safe to unwrap because the queue isn't empty
+    *credits.get_mut(&queue[index].name).unwrap() -= total;
+
+    if let Some(task) = queue.remove(index) {
+        queue.push_back(task);
+    }
+}
+
+/// Shuffles the queue with a generator seeded by the date, so the order is stable within a day but
+/// varies from day to day.
+fn shuffle_for_date(queue: &mut VecDeque<Task>, date: NaiveDate) {
+    // SplitMix64, which is plenty for reordering a handful of chores.
+    let mut state = date.num_days_from_ce() as u64;
+    let mut next = || {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    };
+
+    for i in (1..queue.len()).rev() {
+        let j = (next() % (i as u64 + 1)) as usize;
+        queue.swap(i, j);
+    }
+}
+
+/// Creates a work block for the task.
+fn create_task_block(
+    task: &Task,
+    start_time: NaiveTime,
+    date: NaiveDate,
+    duration: Duration,
+) -> TimeBlock {
+    let end = date.and_time(start_time) + duration;
     TimeBlock::new_named(
         format!("Task - {}", task.name),
         start_time,
-        start_time + Duration::minutes(25),
-        date,
+        end.time(),
         date,
+        end.date(),
     )
 }
 
+/// Rounds a time up to the next multiple of `align_to` since midnight. Times that would round
+/// past midnight are left alone.
+fn align_time(time: NaiveTime, align_to: Option<Duration>) -> NaiveTime {
+    let step = match align_to {
+        Some(step) if step > Duration::zero() => step.num_seconds(),
+        _ => return time,
+    };
+    let seconds = time.num_seconds_from_midnight() as i64;
+    let aligned = (seconds + step - 1) / step * step;
+    NaiveTime::from_num_seconds_from_midnight_opt(aligned as u32, 0).unwrap_or(time)
+}
+
+/// `align_time` for a moment on a given date.
+fn align_at(at: NaiveDateTime, align_to: Option<Duration>) -> NaiveDateTime {
+    at.date().and_time(align_time(at.time(), align_to))
+}
+
+/// When the window ends. A window that ends before it starts on the same date runs past
+/// midnight into the next day.
+fn window_end(block: &TimeBlock) -> NaiveDateTime {
+    let end = block_end(block);
+    if end < block_start(block) && block.start_date == block.end_date {
+        end + Duration::days(1)
+    } else {
+        end
+    }
+}
+
+/// Creates the work block for one pomodoro of the task, `length` long. That's a full pomodoro
+/// unless the block was cut short.
+fn create_pomodoro_block(
+    task: &Task,
+    start_time: NaiveTime,
+    date: NaiveDate,
+    length: Duration,
+) -> TimeBlock {
+    create_task_block(task, start_time, date, length)
+}
+
 fn create_pomodoro_rest(start_time: NaiveTime, date: NaiveDate, duration: Duration) -> TimeBlock {
+    let end = date.and_time(start_time) + duration;
     TimeBlock::new_named(
-        format!("Break ({} minutes)", duration.num_minutes().to_string()),
+        format!("Break ({} minutes)", duration.num_minutes()),
         start_time,
-        start_time + Duration::minutes(25),
-        date,
+        end.time(),
         date,
+        end.date(),
     )
 }
 
+/// Places pinned tasks at their fixed start, each in a single block covering its remaining work
+/// and cut off at the end of the window. Pins outside the window are left out, and in a window
+/// running past midnight, pins before its start fall on the next day. Returns the pinned blocks in
+/// start order along with the tasks that are free to move.
+fn place_pinned_tasks(tasks: Vec<Task>, schedule_block: &TimeBlock) -> (Vec<TimeBlock>, Vec<Task>) {
+    let (pinned, free): (Vec<Task>, Vec<Task>) = tasks
+        .into_iter()
+        .partition(|task| task.fixed_start().is_some());
+
+    let window_start = block_start(schedule_block);
+    let window_end = window_end(schedule_block);
+    let mut blocks: Vec<TimeBlock> = pinned
+        .iter()
+        .filter_map(|task| {
+            let mut start = schedule_block.start_date.and_time(task.fixed_start()?);
+            if start < window_start {
+                start += Duration::days(1);
+            }
+            if start >= window_end {
+                return None;
+            }
+            let duration = task.duration().min(window_end - start);
+            Some(create_task_block(
+                task,
+                start.time(),
+                start.date(),
+                duration,
+            ))
+        })
+        .collect();
+    blocks.sort_by_key(block_start);
+    (blocks, free)
+}
+
+/// The first pinned block that overlaps the span from `start` to `end`.
+fn pinned_overlap(
+    pinned: &[TimeBlock],
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+) -> Option<&TimeBlock> {
+    pinned
+        .iter()
+        .find(|block| block_start(block) < end && block_end(block) > start)
+}
+
+/// How a queue shares out its turns.
+#[derive(Clone, Copy)]
+struct Turns {
+    /// Whether a task goes to the end of the line after each block, rather than keeping its turn
+    /// until it's done.
+    round_robin: bool,
+    /// After this many blocks in a row, a task sits out a turn if another task can go.
+    max_consecutive: Option<usize>,
+}
+
+/// The task that had the last work blocks, and how many it had in a row. Breaks don't interrupt a
+/// run.
+fn last_streak(populated_time_block: &[TimeBlock]) -> Option<(&str, usize)> {
+    let mut blocks = populated_time_block
+        .iter()
+        .rev()
+        .filter(|block| !is_break_block(block));
+    let name = blocks.next().and_then(block_task_name)?;
+    let count = blocks
+        .take_while(|block| block_task_name(block) == Some(name))
+        .count();
+    Some((name, count + 1))
+}
+
+/// Schedules the next task in the queue at the start of the gap, returning whether a block was
+/// emitted. The block is one pomodoro long, or cut short where the gap is. Tasks whose minimum
+/// block is longer than the gap are passed over and stay queued, as is a task that has had
+/// `turns.max_consecutive` blocks in a row while another task can go.
+///
+/// A task with work left goes to the end of the line with `turns.round_robin`, and otherwise
+/// stays next.
+///
+/// Once `max_tasks` distinct tasks have been started, tasks that haven't been started yet are
+/// dropped from the queue rather than scheduled. Tasks already in progress may continue.
 fn handle_task(
     queue: &mut VecDeque<Task>,
-    cur_time: NaiveTime,
-    schedule_date: NaiveDate,
+    gap: &TimeBlock,
     populated_time_block: &mut Vec<TimeBlock>,
-) {
-    if let Some(mut task) = queue.pop_back() {
-        populated_time_block.push(create_pomodoro_block(&task, cur_time, schedule_date));
-        task.do_work(Duration::minutes(25));
+    started_tasks: &mut Vec<String>,
+    max_tasks: Option<usize>,
+    pomodoro: &PomodoroConfig,
+    turns: Turns,
+) -> bool {
+    let available = gap.duration();
+    let capped: Option<String> = last_streak(populated_time_block)
+        .filter(|(_, count)| turns.max_consecutive.is_some_and(|max| *count >= max))
+        .map(|(name, _)| String::from(name));
+    let mut passed_over = vec![];
+    let mut scheduled = false;
+    while let Some(mut task) = queue.pop_back() {
+        if !task.fits_in(available) {
+            passed_over.push(task);
+            continue;
+        }
+        if capped.as_deref() == Some(task.name.as_str())
+            && queue.iter().any(|other| other.fits_in(available))
+        {
+            passed_over.push(task);
+            continue;
+        }
+        if !started_tasks.contains(&task.name) {
+            if max_tasks.is_some_and(|max| started_tasks.len() >= max) {
+                continue;
+            }
+            started_tasks.push(task.name.clone());
+        }
+
+        let length = pomodoro.work().min(available);
+        populated_time_block.push(create_pomodoro_block(
+            &task,
+            gap.start_time,
+            gap.start_date,
+            length,
+        ));
+        task.do_work(length);
         if task.has_work_remaining() {
-            queue.push_front(task);
+            if turns.round_robin {
+                queue.push_front(task);
+            } else {
+                queue.push_back(task);
+            }
         }
+        scheduled = true;
+        break;
+    }
+    while let Some(task) = passed_over.pop() {
+        queue.push_back(task);
+    }
+    scheduled
+}
+
+/// Makes the next High difficulty task that fits in `available` the next one taken from the
+/// queue, ahead of easier tasks of the same class.
+fn bring_forward_difficult(queue: &mut VecDeque<Task>, available: Duration) {
+    let position = queue
+        .iter()
+        .rposition(|task| task.difficulty() == Difficulty::High && task.fits_in(available));
+    if let Some(task) = position.and_then(|position| queue.remove(position)) {
+        queue.push_back(task);
     }
 }
 
@@ -81,27 +538,70 @@ fn handle_task(
 ///
 ///
 /// 1. High Priority Queue: This queue contains tasks that are of high priority
-/// and need to be performed as soon as possible. Tasks in this queue are
-/// scheduled via a Shortest Job First (SJF) strategy. Tie breaking is done via
-/// First Come First Served (FIFO).
+///    and need to be performed as soon as possible. Tasks in this queue are
+///    scheduled via a Shortest Job First (SJF) strategy. Tie breaking is done via
+///    First Come First Served (FIFO). Tasks given a manual order with `reorder-task` go
+///    ahead of the rest, lowest order first. A task keeps its turn until its work is done.
 ///
 /// 2. Medium Priority Queue: The medium priority queue is scheduled after the
-/// medium priority queue is exhausted. It uses the same SJF and FIFO scheme as
-/// the high priority queue.
+///    medium priority queue is exhausted. It uses the same SJF and FIFO scheme as
+///    the high priority queue.
 ///
 /// 3. Low Priority Queue: The low priority queue is scheduled once the High
-/// and Medium queues have been exhausted. To avoid starvation, a low
-/// priority task is forcibly scheduled after 4 high or medium priority tasks
-/// have been scheduled. Under normal circumstances, low priority tasks are
-/// scheduled with a Round-Robin scheduling algorithm that employs a 25
-/// minute time quanta.
+///    and Medium queues have been exhausted. To avoid starvation, a low
+///    priority task is forcibly scheduled after 4 high or medium priority tasks
+///    have been scheduled. Under normal circumstances, low priority tasks are
+///    scheduled with a Round-Robin scheduling algorithm that employs a one
+///    pomodoro time quanta. With `weighted_low`, higher priority tasks in the
+///    class get proportionally more turns.
 ///
 /// With this scheduling stack up, a full pomodoro cycle is allowed to
 /// finish with high and medium priority tasks before moving to lower
 /// priority tasks.
 ///
-fn populate_time_block(tasks: Vec<Task>, schedule_block: TimeBlock) -> Vec<TimeBlock> {
+/// If the context has a daily task cap, no new tasks are started once the cap is reached. With
+/// `max_consecutive_blocks`, a task that has had that many blocks in a row sits out a turn so the
+/// next task in its queue can go.
+///
+/// A task with a minimum block is only started when the gap before the end of the window or the
+/// next pinned task is at least that long.
+///
+/// With `skip_transition_break`, the short break after the last High or Medium block is left out
+/// when Low work follows. A long break that's due is kept.
+///
+/// Tasks pinned to a fixed start are placed before anything else, whatever their priority. Work
+/// that would run into a pinned block starts after it instead, and a break is cut short.
+///
+/// With an energy curve, low energy hours pull from the Low queue (then Medium) first, leaving
+/// High priority work to the sharper hours. Breaks keep to the pomodoro cadence whatever the
+/// energy. In high energy hours, High difficulty tasks go ahead of easier ones in their queue.
+///
+/// A window that ends before it starts runs past midnight, and blocks after midnight fall on the
+/// next day.
+///
+/// Focus contexts skip all of this; see `populate_focus_block`.
+///
+fn populate_time_block(
+    tasks: Vec<Task>,
+    schedule_block: TimeBlock,
+    context: &Context,
+    options: &ScheduleOptions,
+) -> Vec<TimeBlock> {
+    // Tasks with less than a minute of work left are treated as done, rather than being given
+    // a block they don't need.
+    let tasks: Vec<Task> = tasks
+        .into_iter()
+        .filter(|task| task.duration() >= Duration::minutes(1))
+        .collect();
+
+    if context.focus_mode {
+        return populate_focus_block(tasks, schedule_block, context);
+    }
+
+    let (pinned, tasks) = place_pinned_tasks(tasks, &schedule_block);
+
     let mut populated_time_block = Vec::new();
+    let mut started_tasks: Vec<String> = vec![];
     let mut high_med_prio_tasks = 0;
     let mut total_tasks = 0;
     let mut forced_low_pri = false;
@@ -109,117 +609,1052 @@ fn populate_time_block(tasks: Vec<Task>, schedule_block: TimeBlock) -> Vec<TimeB
     const FORCED_LOW_PRIO_TASK: i32 = 4;
     let mut time_block_full = false;
 
-    let mut high_priority_queue = get_priority_queue(&tasks, PriorityClass::High);
-    let mut med_priority_queue = get_priority_queue(&tasks, PriorityClass::Medium);
-    let mut low_priority_queue = get_priority_queue(&tasks, PriorityClass::Low);
+    // The weighted strategy runs everything through the high priority queue, leaving the others
+    // empty so the rest of the loop behaves the same.
+    let (mut high_priority_queue, mut med_priority_queue, mut low_priority_queue) =
+        match options.strategy {
+            SchedulingStrategy::Multilevel => (
+                get_priority_queue(&tasks, PriorityClass::High),
+                get_priority_queue(&tasks, PriorityClass::Medium),
+                get_priority_queue(&tasks, PriorityClass::Low),
+            ),
+            SchedulingStrategy::WeightedScore => {
+                (get_weighted_queue(&tasks), VecDeque::new(), VecDeque::new())
+            }
+        };
+    if options.shuffle_low {
+        shuffle_for_date(&mut low_priority_queue, schedule_block.start_date);
+    }
+    // Pick up the round-robin where earlier days left off.
+    if !low_priority_queue.is_empty() {
+        for _ in 0..options.low_turns_before % low_priority_queue.len() {
+            if let Some(task) = low_priority_queue.pop_back() {
+                low_priority_queue.push_front(task);
+            }
+        }
+    }
 
-    let mut cur_time: NaiveTime = schedule_block.start_time;
+    // Times are tracked with their date, so a window running past midnight carries on into the
+    // next day.
+    let window_end = window_end(&schedule_block);
+    let mut cur_time = align_at(block_start(&schedule_block), options.align_to);
+    let mut low_credits: HashMap<String, i64> = HashMap::new();
+    // Low tasks take turns a block at a time, while High and Medium tasks keep going until
+    // they're done.
+    let low_turns = Turns {
+        round_robin: true,
+        max_consecutive: options.max_consecutive_blocks,
+    };
+    let high_med_turns = Turns {
+        round_robin: false,
+        ..low_turns
+    };
 
     while !time_block_full {
-        if !high_priority_queue.is_empty() || !med_priority_queue.is_empty() {
+        let had_high_med = !high_priority_queue.is_empty() || !med_priority_queue.is_empty();
+        if let Some(pin) = pinned_overlap(&pinned, cur_time, cur_time + options.pomodoro.work()) {
+            cur_time = align_at(block_end(pin), options.align_to);
+            time_block_full = cur_time >= window_end;
+            continue;
+        }
+
+        let gap_end = pinned
+            .iter()
+            .map(block_start)
+            .filter(|start| *start >= cur_time)
+            .fold(window_end, NaiveDateTime::min);
+        let available = gap_end - cur_time;
+        let gap = TimeBlock::new(
+            cur_time.time(),
+            gap_end.time(),
+            cur_time.date(),
+            gap_end.date(),
+        );
+        let ready = |queue: &VecDeque<Task>| queue.iter().any(|task| task.fits_in(available));
+        let (high_ready, med_ready, low_ready) = (
+            ready(&high_priority_queue),
+            ready(&med_priority_queue),
+            ready(&low_priority_queue),
+        );
+
+        // High energy hours go to the hardest work in whichever queue is up next.
+        if options.energy_curve.is_high_at(cur_time.time()) {
+            for queue in [
+                &mut high_priority_queue,
+                &mut med_priority_queue,
+                &mut low_priority_queue,
+            ] {
+                bring_forward_difficult(queue, available);
+            }
+        }
+
+        // Low energy hours go to the least important work, saving the rest for when it counts.
+        let low_energy = options.energy_curve.is_low_at(cur_time.time());
+        if low_energy && options.strategy == SchedulingStrategy::WeightedScore {
+            if let Some(task) = high_priority_queue.pop_front() {
+                high_priority_queue.push_back(task);
+            }
+        }
+
+        let scheduled = if low_energy
+            && options.strategy == SchedulingStrategy::Multilevel
+            && (low_ready || med_ready)
+        {
+            let (queue, turns) = if low_ready {
+                if options.weighted_low {
+                    select_weighted_low(&mut low_priority_queue, &mut low_credits);
+                }
+                (&mut low_priority_queue, low_turns)
+            } else {
+                (&mut med_priority_queue, high_med_turns)
+            };
+            handle_task(
+                queue,
+                &gap,
+                &mut populated_time_block,
+                &mut started_tasks,
+                context.max_tasks_per_day,
+                &options.pomodoro,
+                turns,
+            )
+        } else if high_ready || med_ready {
             // Force inject low priority task if necessary
-            if high_med_prio_tasks >= 1 && high_med_prio_tasks % FORCED_LOW_PRIO_TASK == 0 && !forced_low_pri {
+            if high_med_prio_tasks >= 1
+                && high_med_prio_tasks % FORCED_LOW_PRIO_TASK == 0
+                && !forced_low_pri
+                && low_ready
+            {
+                forced_low_pri = true;
+
+                if options.weighted_low {
+                    select_weighted_low(&mut low_priority_queue, &mut low_credits);
+                }
                 handle_task(
                     &mut low_priority_queue,
-                    cur_time,
-                    schedule_block.start_date,
+                    &gap,
                     &mut populated_time_block,
-                );
+                    &mut started_tasks,
+                    context.max_tasks_per_day,
+                    &options.pomodoro,
+                    low_turns,
+                )
+            } else {
+                forced_low_pri = false;
+                high_med_prio_tasks += 1;
 
-                forced_low_pri = true;
+                let queue = if high_ready {
+                    &mut high_priority_queue
+                } else {
+                    &mut med_priority_queue
+                };
+                handle_task(
+                    queue,
+                    &gap,
+                    &mut populated_time_block,
+                    &mut started_tasks,
+                    context.max_tasks_per_day,
+                    &options.pomodoro,
+                    high_med_turns,
+                )
             }
-            else {
-                if !high_priority_queue.is_empty() {
-                    handle_task(
-                        &mut high_priority_queue,
-                        cur_time,
-                        schedule_block.start_date,
-                        &mut populated_time_block,
-                    );
-                    high_med_prio_tasks += 1;
-                } else if !med_priority_queue.is_empty() {
-                    handle_task(
-                        &mut med_priority_queue,
-                        cur_time,
-                        schedule_block.start_date,
-                        &mut populated_time_block,
-                    );
-                    high_med_prio_tasks += 1;
-                }                
-
-                forced_low_pri = false;
+        } else if low_ready {
+            if options.weighted_low {
+                select_weighted_low(&mut low_priority_queue, &mut low_credits);
             }
-
-            cur_time += Duration::minutes(25);
-        } else if !low_priority_queue.is_empty() {
             handle_task(
                 &mut low_priority_queue,
-                cur_time,
-                schedule_block.start_date,
+                &gap,
                 &mut populated_time_block,
-            );
-            cur_time += Duration::minutes(25);
+                &mut started_tasks,
+                context.max_tasks_per_day,
+                &options.pomodoro,
+                low_turns,
+            )
+        } else if let Some(pin) = pinned.iter().find(|pin| block_start(pin) == gap_end) {
+            // Nothing fits before the pin, but there may be room after it.
+            cur_time = align_at(block_end(pin), options.align_to);
+            time_block_full = cur_time >= window_end;
+            continue;
         } else {
             // No tasks left!!
             time_block_full = true;
+            false
+        };
+
+        if !scheduled {
+            continue;
+        }
+
+        if options.strategy == SchedulingStrategy::WeightedScore {
+            // Remaining durations changed, so the scores did too.
+            sort_by_weighted_score(&mut high_priority_queue);
         }
 
+        // The block just scheduled may have been cut short by the end of the window.
+        let worked_until = populated_time_block
+            .last()
+            .map_or(cur_time + options.pomodoro.work(), block_end);
+        cur_time = align_at(worked_until, options.align_to);
         total_tasks += 1;
+        // The block just scheduled finished the High and Medium work, and Low work is up next.
+        let at_transition = had_high_med
+            && high_priority_queue.is_empty()
+            && med_priority_queue.is_empty()
+            && !low_priority_queue.is_empty();
 
-        if cur_time >= schedule_block.end_time {
+        if cur_time >= window_end {
             time_block_full = true;
         } else if !time_block_full {
-
-            let rest_duration = if total_tasks % 4 == 0 {
-                Duration::minutes(20)
+            let rest_duration = if options.pomodoro.is_long_break(total_tasks) {
+                options.pomodoro.long_break()
+            } else if at_transition && options.skip_transition_break {
+                Duration::zero()
             } else {
-                Duration::minutes(5)
+                options.pomodoro.short_break()
+            };
+            let rest_duration = match pinned_overlap(&pinned, cur_time, cur_time + rest_duration) {
+                Some(pin) => (block_start(pin) - cur_time).max(Duration::zero()),
+                None => rest_duration,
             };
-            populated_time_block.push(create_pomodoro_rest(
-                cur_time,
-                schedule_block.start_date,
-                rest_duration,
+            // A break that would run past the end of the window is dropped, since no work could
+            // follow it anyway.
+            if rest_duration > window_end - cur_time {
+                time_block_full = true;
+                continue;
+            }
+            if options.breaks == BreakMode::Explicit && rest_duration > Duration::zero() {
+                populated_time_block.push(create_pomodoro_rest(
+                    cur_time.time(),
+                    cur_time.date(),
+                    rest_duration,
+                ));
+            }
+            cur_time = align_at(cur_time + rest_duration, options.align_to);
+            time_block_full = cur_time >= window_end;
+        }
+    }
+
+    populated_time_block.extend(pinned);
+    populated_time_block.sort_by_key(block_start);
+    populated_time_block
+}
+
+/// Scheduling for focus contexts. Each task gets a single block covering its full remaining
+/// duration (cut off at the end of the window), in priority class order, with no breaks.
+fn populate_focus_block(
+    tasks: Vec<Task>,
+    schedule_block: TimeBlock,
+    context: &Context,
+) -> Vec<TimeBlock> {
+    let mut populated_time_block = Vec::new();
+    let window_end = window_end(&schedule_block);
+    let mut cur_time = block_start(&schedule_block);
+
+    let queues = [
+        PriorityClass::High,
+        PriorityClass::Medium,
+        PriorityClass::Low,
+    ]
+    .into_iter()
+    .map(|class| get_priority_queue(&tasks, class));
+
+    for mut queue in queues {
+        while let Some(task) = queue.pop_back() {
+            let available = window_end - cur_time;
+            if !task.fits_in(available) {
+                continue;
+            }
+            if available <= Duration::zero()
+                || context
+                    .max_tasks_per_day
+                    .is_some_and(|max| populated_time_block.len() >= max)
+            {
+                return populated_time_block;
+            }
+
+            let duration = task.duration().min(available);
+            populated_time_block.push(create_task_block(
+                &task,
+                cur_time.time(),
+                cur_time.date(),
+                duration,
             ));
-            cur_time += rest_duration;
+            cur_time += duration;
         }
     }
 
     populated_time_block
 }
 
+/// Why a task got no time in a schedule.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum UnscheduledReason {
+    /// Its context was active but ran out of room before reaching it.
+    Overflow(String),
+    /// It refers to a context that doesn't exist, so nothing will ever schedule it.
+    Orphaned,
+}
+
+impl std::fmt::Display for UnscheduledReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            UnscheduledReason::Overflow(context) => {
+                write!(f, "overflow: no room left in {}", context)
+            }
+            UnscheduledReason::Orphaned => write!(f, "orphaned: no such context"),
+        }
+    }
+}
+
+/// Where an open task stands in a day's schedule.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TaskStanding {
+    /// It got all the work it has left.
+    Scheduled(Duration),
+    /// It got some of its work, and the rest waits for another day.
+    Deferred { worked: Duration, left: Duration },
+    /// It's snoozed until the given date.
+    Snoozed(NaiveDate),
+    /// It has priority 0, and those tasks aren't being scheduled.
+    ZeroPriority,
+    /// It has no context, so nothing will schedule it.
+    NoContext,
+    /// None of its contexts has a window that day.
+    Inactive(Vec<String>),
+    /// Its contexts with a window that day were left out with `--context`.
+    Excluded(Vec<String>),
+    /// The day's schedule is locked, and it isn't in it.
+    LeftOutOfLock,
+    /// Its context was tried but didn't give it any time.
+    Unscheduled(UnscheduledReason),
+}
+
+impl std::fmt::Display for TaskStanding {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TaskStanding::Scheduled(worked) => write!(f, "scheduled: {}", format_duration(*worked)),
+            TaskStanding::Deferred { worked, left } => write!(
+                f,
+                "deferred: {} today, {} left for another day",
+                format_duration(*worked),
+                format_duration(*left)
+            ),
+            TaskStanding::Snoozed(until) => write!(f, "snoozed: until {}", until),
+            TaskStanding::ZeroPriority => write!(f, "skipped: priority 0 tasks aren't scheduled"),
+            TaskStanding::NoContext => write!(f, "no context: nothing will schedule it"),
+            TaskStanding::Inactive(contexts) => {
+                write!(
+                    f,
+                    "inactive: no window in {} that day",
+                    contexts.join(" or ")
+                )
+            }
+            TaskStanding::Excluded(contexts) => write!(
+                f,
+                "excluded: {} wasn't picked with --context",
+                contexts.join(" or ")
+            ),
+            TaskStanding::LeftOutOfLock => write!(f, "locked out: not in the locked schedule"),
+            TaskStanding::Unscheduled(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+/// Lists each task with where it stands, one per line, under a heading.
+pub fn render_standings(standings: &[(String, TaskStanding)]) -> String {
+    let mut output = String::from("Tasks:\n");
+    for (name, standing) in standings {
+        output.push_str(&format!("  {} ({})\n", name, standing));
+    }
+    output
+}
+
+/// A task left out of a schedule, and why.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnscheduledTask {
+    pub name: String,
+    /// The context the task is listed under first, which tells apart tasks that share a name.
+    pub context: Option<String>,
+    pub reason: UnscheduledReason,
+}
+
+/// A task that won't be finished by its deadline.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DeadlineRisk {
+    pub name: String,
+    pub deadline: NaiveDate,
+    pub kind: DeadlineKind,
+    /// Whether the deadline was already over on the scheduled day.
+    pub overdue: bool,
+}
+
+impl std::fmt::Display for DeadlineRisk {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.overdue {
+            write!(
+                f,
+                "'{}' is past its {} deadline of {}",
+                self.name, self.kind, self.deadline
+            )
+        } else {
+            write!(
+                f,
+                "'{}' won't be finished by its {} deadline of {}",
+                self.name, self.kind, self.deadline
+            )
+        }
+    }
+}
+
+/// The open tasks due by `date` that the day's schedule doesn't give enough time to finish.
+pub fn deadline_risks(
+    tasks: &[Task],
+    schedule: &[TimeBlock],
+    date: NaiveDate,
+) -> Vec<DeadlineRisk> {
+    tasks
+        .iter()
+        .filter(|task| task.is_schedulable())
+        .filter_map(|task| {
+            let deadline = task.deadline().filter(|deadline| *deadline <= date)?;
+            let worked = schedule
+                .iter()
+                .filter(|block| is_block_for(block, task))
+                .fold(Duration::zero(), |total, block| total + block.duration());
+            (deadline < date || worked < task.duration()).then(|| DeadlineRisk {
+                name: task.name.clone(),
+                deadline,
+                kind: task.deadline_kind(),
+                overdue: deadline < date,
+            })
+        })
+        .collect()
+}
+
+/// The blocks of a schedule along with the tasks that didn't make it in.
+#[derive(Clone, Debug, Default)]
+pub struct ScheduleResult {
+    pub blocks: Vec<TimeBlock>,
+    pub unscheduled: Vec<UnscheduledTask>,
+    /// How many work blocks went to Low class tasks, so the next day's round-robin can carry on
+    /// from there.
+    pub low_turns: usize,
+}
+
+/// The parts of `window` not covered by any of the `claimed` windows, in order. Windows running
+/// past midnight are left whole.
+fn free_windows(window: &TimeBlock, claimed: &[TimeBlock]) -> Vec<TimeBlock> {
+    if window.end_time <= window.start_time {
+        return vec![window.clone()];
+    }
+    let mut claimed: Vec<&TimeBlock> = claimed
+        .iter()
+        .filter(|block| block.start_time < block.end_time)
+        .collect();
+    claimed.sort_by_key(|block| block.start_time);
+
+    let mut windows = vec![];
+    let mut start = window.start_time;
+    for block in claimed {
+        if block.end_time <= start || block.start_time >= window.end_time {
+            continue;
+        }
+        if block.start_time > start {
+            windows.push(TimeBlock::new(
+                start,
+                block.start_time,
+                window.start_date,
+                window.end_date,
+            ));
+        }
+        start = start.max(block.end_time);
+    }
+    if start < window.end_time {
+        windows.push(TimeBlock::new(
+            start,
+            window.end_time,
+            window.start_date,
+            window.end_date,
+        ));
+    }
+    windows
+}
+
+/// Trims blocks to end by `end`, dropping any that would start at or after it.
+fn clip_to_end(blocks: &mut Vec<TimeBlock>, end: NaiveTime) {
+    blocks.retain(|block| block.start_time < end);
+    for block in blocks.iter_mut() {
+        if block.end_time > end {
+            block.end_time = end;
+        }
+    }
+}
+
+/// The total time the blocks give the task.
+pub fn worked_on(blocks: &[TimeBlock], task: &Task) -> Duration {
+    blocks
+        .iter()
+        .filter(|block| is_block_for(block, task))
+        .fold(Duration::zero(), |total, block| total + block.duration())
+}
+
 /// This function builds a schedule for a single day.
-/// TODO: Do more than one day.
+///
+/// A task that lists several contexts is offered to each in turn, in its order of preference,
+/// until one gives it all the time it needs. A context that isn't active that day is skipped.
+///
+/// Plans that span several days, like `PreemptApp::plan_until_done` and `timeline --week`, call
+/// this once per day.
 pub fn build_schedule(
-    contexts: &Vec<Context>,
-    tasks: &Vec<Task>,
+    contexts: &[Context],
+    tasks: &[Task],
     schedule_block: TimeBlock,
-) -> Vec<TimeBlock> {
-    let mut schedule: Vec<TimeBlock> = vec![];
+    options: &ScheduleOptions,
+) -> ScheduleResult {
+    let mut result = ScheduleResult::default();
+    let tasks = apply_aging(
+        tasks,
+        schedule_block
+            .start_date
+            .and_time(schedule_block.start_time),
+        options,
+    );
 
-    // First, find which contexts are active during this time block.
-    let mut active_contexts: Vec<Context> = vec![];
+    // Higher priority contexts claim their windows first. Ties go to the context defined first.
+    // The blocks are still listed in the order the contexts were defined.
+    let mut by_priority: Vec<(usize, &Context)> = contexts.iter().enumerate().collect();
+    by_priority.sort_by_key(|(_, context)| std::cmp::Reverse(context.priority));
+    let active: Vec<(usize, &Context, Vec<TimeBlock>)> = by_priority
+        .into_iter()
+        .filter(|(_, context)| {
+            options
+                .only_contexts
+                .as_ref()
+                .is_none_or(|only| only.contains(&context.name))
+        })
+        .map(|(index, context)| {
+            (
+                index,
+                context,
+                context.get_timeblock(schedule_block.start_date),
+            )
+        })
+        .filter(|(_, _, timeblocks)| !timeblocks.is_empty())
+        .collect();
+    let mut claimed: Vec<TimeBlock> = vec![];
+    let mut context_blocks: Vec<(usize, Vec<TimeBlock>)> = vec![];
+    // Work is taken off as it's scheduled, so a task with several contexts only gets what's left.
+    let mut remaining = tasks.clone();
+    // Each task that wanted time, with the last context it was tried in.
+    let mut tried: Vec<(Task, String)> = vec![];
 
-    for context in contexts {
-        match context.get_timeblock(schedule_block.start_date) {
-            Some(timeblock) => {
-                schedule.append(&mut populate_time_block(
-                    Task::filter_context_tasks(context, tasks.clone()),
-                    timeblock,
-                ));
+    for (position, (index, context, timeblocks)) in active.iter().enumerate() {
+        let later = &active[position + 1..];
+        let pending = |name: &str| {
+            later
+                .iter()
+                .any(|(_, other, _)| name_key(&other.name) == name_key(name))
+        };
+        let open: Vec<Task> = remaining
+            .iter()
+            .filter(|task| task.duration() >= Duration::minutes(1))
+            .cloned()
+            .collect();
+        let context_tasks = Task::filter_context_tasks(context, open, pending);
+        let mut context_remaining = context_tasks.clone();
+        let mut blocks = vec![];
+        for timeblock in timeblocks {
+            for window in free_windows(timeblock, &claimed) {
+                if context_tasks.is_empty() {
+                    if options.show_free {
+                        let mut free = window.clone();
+                        free.name = Some(format!("Free: {}", context.name));
+                        free.context = Some(context.name.clone());
+                        blocks.push(free);
+                    }
+                    continue;
+                }
+                let mut window_blocks = populate_time_block(
+                    context_remaining.clone(),
+                    window.clone(),
+                    context,
+                    options,
+                );
+                if window.end_time < timeblock.end_time {
+                    // Cut short by another context, so nothing may run into its time.
+                    clip_to_end(&mut window_blocks, window.end_time);
+                }
+                for block in &mut window_blocks {
+                    block.context = Some(context.name.clone());
+                }
+                for task in &mut context_remaining {
+                    task.do_work(worked_on(&window_blocks, task));
+                }
+                blocks.append(&mut window_blocks);
+            }
+        }
+        claimed.extend(timeblocks.iter().cloned());
+
+        for task in &context_tasks {
+            let same_task =
+                |other: &Task| other.name == task.name && other.contexts() == task.contexts();
+            if let Some(stored) = remaining.iter_mut().find(|stored| same_task(stored)) {
+                stored.do_work(worked_on(&blocks, task));
             }
-            None => {}
+            match tried.iter_mut().find(|(other, _)| same_task(other)) {
+                Some(entry) => entry.1 = context.name.clone(),
+                None => tried.push((task.clone(), context.name.clone())),
+            }
+        }
+        context_blocks.push((*index, blocks));
+    }
+    context_blocks.sort_by_key(|(index, _)| *index);
+    for (_, mut blocks) in context_blocks {
+        result.blocks.append(&mut blocks);
+    }
+
+    for (task, context) in tried {
+        if worked_on(&result.blocks, &task).is_zero() {
+            result.unscheduled.push(UnscheduledTask {
+                context: task.context().map(String::from),
+                name: task.name,
+                reason: UnscheduledReason::Overflow(context),
+            });
         }
     }
 
+    result.low_turns = result
+        .blocks
+        .iter()
+        .filter(|block| {
+            tasks.iter().any(|task| {
+                get_task_priority(task) == PriorityClass::Low && is_block_for(block, task)
+            })
+        })
+        .count();
+
+    for task in &tasks {
+        let orphaned = task.context().is_some()
+            && !contexts
+                .iter()
+                .any(|context| task.is_context_match(&context.name));
+        if orphaned && task.is_schedulable() {
+            result.unscheduled.push(UnscheduledTask {
+                name: task.name.clone(),
+                context: task.context().map(String::from),
+                reason: UnscheduledReason::Orphaned,
+            });
+        }
+    }
+
+    result
+}
+
+/// Prints the tasks left out of a schedule, if there are any.
+pub fn print_unscheduled(unscheduled: &[UnscheduledTask]) {
+    print!("{}", render_unscheduled(unscheduled));
+}
+
+/// The text `print_unscheduled` prints, empty when every task got time.
+pub fn render_unscheduled(unscheduled: &[UnscheduledTask]) -> String {
+    if unscheduled.is_empty() {
+        return String::new();
+    }
+    let mut output = String::from("Unscheduled:\n");
+    for task in unscheduled {
+        output.push_str(&format!("  {} ({})\n", task.name, task.reason));
+    }
+    output
+}
+
+/// The block in progress at the given moment, if any.
+pub fn current_block(schedule: &[TimeBlock], at: NaiveDateTime) -> Option<&TimeBlock> {
+    schedule.iter().find(|block| block.contains(at))
+}
+
+/// The first block starting after the given moment.
+pub fn next_block(schedule: &[TimeBlock], at: NaiveDateTime) -> Option<&TimeBlock> {
     schedule
+        .iter()
+        .filter(|block| block.start_date.and_time(block.start_time) > at)
+        .min_by_key(|block| block.start_date.and_time(block.start_time))
 }
 
-pub fn print_schedule(schedule: Vec<TimeBlock>) {
-    for block in schedule {
+/// Whether the block is a scheduled task, based on the name the scheduler gives it.
+pub fn is_task_block(block: &TimeBlock) -> bool {
+    block
+        .name
+        .as_deref()
+        .is_some_and(|name| name.starts_with("Task - "))
+}
+
+/// The name of the task a work block was scheduled for.
+pub fn block_task_name(block: &TimeBlock) -> Option<&str> {
+    block.name.as_deref()?.strip_prefix("Task - ")
+}
+
+/// Whether a work block was scheduled for the task: it has the task's name and, when it was
+/// scheduled in a context, that context is one of the task's.
+pub fn is_block_for(block: &TimeBlock, task: &Task) -> bool {
+    block_task_name(block) == Some(task.name.as_str())
+        && block
+            .context
+            .as_deref()
+            .is_none_or(|context| task.is_context_match(context))
+}
+
+/// Whether the block is a scheduled break, based on the name the scheduler gives it.
+pub fn is_break_block(block: &TimeBlock) -> bool {
+    block
+        .name
+        .as_deref()
+        .is_some_and(|name| name.starts_with("Break"))
+}
+
+/// Whether the block is an idle context window shown with `show_free`.
+pub fn is_free_block(block: &TimeBlock) -> bool {
+    block
+        .name
+        .as_deref()
+        .is_some_and(|name| name.starts_with("Free: "))
+}
+
+/// Totals for a built schedule.
+pub struct ScheduleSummary {
+    pub ends_at: Option<NaiveTime>,
+    pub work: Duration,
+    pub breaks: Duration,
+    /// How far the work could run over or under, from the estimate uncertainty of the tasks in
+    /// the schedule. Zero unless added with `with_uncertainty`.
+    pub uncertainty: Duration,
+}
+
+impl ScheduleSummary {
+    /// Adds up the estimate uncertainty of each task that has work in the schedule.
+    pub fn with_uncertainty(mut self, schedule: &[TimeBlock], tasks: &[Task]) -> ScheduleSummary {
+        self.uncertainty = tasks
+            .iter()
+            .filter(|task| !worked_on(schedule, task).is_zero())
+            .filter_map(|task| task.estimate_uncertainty())
+            .fold(Duration::zero(), |total, uncertainty| total + uncertainty);
+        self
+    }
+
+    /// The work if every estimate runs short by its full uncertainty.
+    pub fn optimistic_work(&self) -> Duration {
+        (self.work - self.uncertainty).max(Duration::zero())
+    }
+
+    /// The work if every estimate runs long by its full uncertainty.
+    pub fn pessimistic_work(&self) -> Duration {
+        self.work + self.uncertainty
+    }
+
+    /// The earliest and latest the plan could end, given the uncertainty.
+    pub fn end_range(&self) -> Option<(NaiveTime, NaiveTime)> {
+        self.ends_at.map(|ends_at| {
+            (
+                ends_at - (self.work - self.optimistic_work()),
+                ends_at + self.uncertainty,
+            )
+        })
+    }
+
+    /// Break time as a rounded percentage of work time, or `None` when there's no work.
+    pub fn break_percent(&self) -> Option<i64> {
+        let work = self.work.num_seconds();
+        if work == 0 {
+            return None;
+        }
+        Some((self.breaks.num_seconds() as f64 * 100.0 / work as f64).round() as i64)
+    }
+}
+
+pub fn summarize(schedule: &[TimeBlock]) -> ScheduleSummary {
+    ScheduleSummary {
+        ends_at: schedule
+            .iter()
+            .filter(|block| !is_free_block(block))
+            .max_by_key(|block| block.end_date.and_time(block.end_time))
+            .map(|block| block.end_time),
+        work: schedule
+            .iter()
+            .filter(|block| is_task_block(block))
+            .fold(Duration::zero(), |total, block| total + block.duration()),
+        breaks: schedule
+            .iter()
+            .filter(|block| is_break_block(block))
+            .fold(Duration::zero(), |total, block| total + block.duration()),
+        uncertainty: Duration::zero(),
+    }
+}
+
+/// Renders the work and break totals, e.g. "Work 3h 45m / Break 1h (27% break)".
+pub fn render_break_ratio(summary: &ScheduleSummary) -> String {
+    let totals = format!(
+        "Work {} / Break {}",
+        format_duration(summary.work),
+        format_duration(summary.breaks)
+    );
+    match summary.break_percent() {
+        Some(percent) => format!("{} ({}% break)", totals, percent),
+        None => totals,
+    }
+}
+
+pub fn print_summary(summary: &ScheduleSummary) {
+    println!("{}", render_summary(summary));
+}
+
+/// Renders the summary line. With uncertain estimates, it gives the range the plan could end in
+/// and the work as "50m (±15m)".
+pub fn render_summary(summary: &ScheduleSummary) -> String {
+    let ends_at = match summary.ends_at {
+        Some(ends_at) => ends_at,
+        None => return String::from("Nothing planned."),
+    };
+    match summary.end_range() {
+        Some((earliest, latest)) if !summary.uncertainty.is_zero() => format!(
+            "Plan ends at {}, between {} and {}, with {} of work and {} of breaks.",
+            ends_at.format("%H:%M"),
+            earliest.format("%H:%M"),
+            latest.format("%H:%M"),
+            format_estimate(summary.work, Some(summary.uncertainty)),
+            format_duration(summary.breaks)
+        ),
+        _ => format!(
+            "Plan ends at {} with {} of work and {} of breaks.",
+            ends_at.format("%H:%M"),
+            format_duration(summary.work),
+            format_duration(summary.breaks)
+        ),
+    }
+}
+
+/// A block that kept its name but changed time between two schedules.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MovedBlock {
+    pub old: TimeBlock,
+    pub new: TimeBlock,
+    /// How far the start moved; negative when it moved earlier.
+    pub shift: Duration,
+}
+
+/// What changed between two schedules.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScheduleDiff {
+    pub added: Vec<TimeBlock>,
+    pub removed: Vec<TimeBlock>,
+    pub moved: Vec<MovedBlock>,
+}
+
+impl ScheduleDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.moved.is_empty()
+    }
+}
+
+fn block_start(block: &TimeBlock) -> NaiveDateTime {
+    block.start_date.and_time(block.start_time)
+}
+
+fn block_end(block: &TimeBlock) -> NaiveDateTime {
+    block.end_date.and_time(block.end_time)
+}
+
+/// Compares two schedules. Blocks are matched by name, in order, so the second "Task - X" in the
+/// old schedule pairs with the second one in the new. Matched blocks that start at a different time
+/// are reported as moved; unmatched ones as added or removed.
+pub fn diff(old: &[TimeBlock], new: &[TimeBlock]) -> ScheduleDiff {
+    let mut result = ScheduleDiff::default();
+    let mut unmatched: Vec<&TimeBlock> = new.iter().collect();
+
+    for old_block in old {
+        match unmatched
+            .iter()
+            .position(|block| block.name == old_block.name)
+        {
+            Some(index) => {
+                let new_block = unmatched.remove(index);
+                if new_block.start_time != old_block.start_time
+                    || new_block.end_time != old_block.end_time
+                    || new_block.start_date != old_block.start_date
+                {
+                    result.moved.push(MovedBlock {
+                        old: old_block.clone(),
+                        new: new_block.clone(),
+                        shift: block_start(new_block) - block_start(old_block),
+                    });
+                }
+            }
+            None => result.removed.push(old_block.clone()),
+        }
+    }
+    result.added = unmatched.into_iter().cloned().collect();
+
+    result
+}
+
+/// A block as "HH:MM–HH:MM name".
+pub fn format_block_line(block: &TimeBlock) -> String {
+    format!(
+        "{}–{} {}",
+        block.start_time.format("%H:%M"),
+        block.end_time.format("%H:%M"),
+        block.name.as_deref().unwrap_or("Unnamed item")
+    )
+}
+
+pub fn print_diff(diff: &ScheduleDiff) {
+    if diff.is_empty() {
+        println!("No changes.");
+        return;
+    }
+    for block in &diff.removed {
+        println!("- {}", format_block_line(block));
+    }
+    for block in &diff.added {
+        println!("+ {}", format_block_line(block));
+    }
+    for moved in &diff.moved {
+        let (sign, shift) = if moved.shift < Duration::zero() {
+            ("-", -moved.shift)
+        } else {
+            ("+", moved.shift)
+        };
         println!(
-            "{start} - {end} | {block_name}",
-            start = block.start_time.to_string(),
-            end = block.end_time.to_string(),
+            "~ {} moved {}{} ({} -> {})",
+            moved.new.name.as_deref().unwrap_or("Unnamed item"),
+            sign,
+            format_duration(shift),
+            moved.old.start_time.format("%H:%M"),
+            moved.new.start_time.format("%H:%M")
+        );
+    }
+}
+
+/// Merges runs of adjacent blocks that share a name and context into a single block spanning the
+/// whole run. Blocks separated by a differently named block are left alone.
+pub fn coalesce(blocks: Vec<TimeBlock>) -> Vec<TimeBlock> {
+    let mut coalesced: Vec<TimeBlock> = Vec::with_capacity(blocks.len());
+
+    for block in blocks {
+        if let Some(last) = coalesced.last_mut() {
+            if last.name.is_some() && last.name == block.name && last.context == block.context {
+                last.end_time = block.end_time;
+                last.end_date = block.end_date;
+                continue;
+            }
+        }
+        coalesced.push(block);
+    }
+
+    coalesced
+}
+
+/// The seven dates of the week containing `date`, starting from `week_start`.
+pub fn week_dates(date: NaiveDate, week_start: Weekday) -> Vec<NaiveDate> {
+    let offset =
+        (7 + date.weekday().num_days_from_monday() - week_start.num_days_from_monday()) % 7;
+    let first = date - Duration::days(offset as i64);
+    (0..7).map(|i| first + Duration::days(i)).collect()
+}
+
+/// The header shown above a day in multi-day output, e.g. "Mon 2024-06-03".
+pub fn day_header(date: NaiveDate) -> String {
+    date.format("%a %Y-%m-%d").to_string()
+}
+
+/// The day header prefixed with the ISO week number, e.g. "W23 Mon 2024-06-03".
+pub fn week_day_header(date: NaiveDate) -> String {
+    format!("W{:02} {}", date.iso_week().week(), day_header(date))
+}
+
+/// Renders the schedule as a Markdown checklist with a `##` header per date. Work blocks become
+/// unchecked checkboxes and everything else a plain bullet.
+pub fn to_markdown(schedule: &[TimeBlock]) -> String {
+    let mut markdown = String::new();
+    let mut current_date: Option<NaiveDate> = None;
+
+    for block in schedule {
+        if current_date != Some(block.start_date) {
+            if current_date.is_some() {
+                markdown.push('\n');
+            }
+            markdown.push_str(&format!("## {}\n\n", block.start_date));
+            current_date = Some(block.start_date);
+        }
+
+        let marker = if is_task_block(block) { "- [ ]" } else { "-" };
+        markdown.push_str(&format!(
+            "{} {}–{} {}\n",
+            marker,
+            block.start_time.format("%H:%M"),
+            block.end_time.format("%H:%M"),
+            block.name.as_deref().unwrap_or("Unnamed item")
+        ));
+    }
+
+    markdown
+}
+
+/// Escapes a name for the compact format, so a `|` inside it can't be mistaken for a separator.
+fn escape_compact(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('|', "\\|")
+}
+
+/// Renders one `start|end|name` line per block, for status bars and scripts. Times are `HH:MM`
+/// and `|` or `\` in names are escaped with a backslash.
+pub fn to_compact(schedule: &[TimeBlock]) -> String {
+    schedule
+        .iter()
+        .map(|block| {
+            format!(
+                "{}|{}|{}\n",
+                block.start_time.format("%H:%M"),
+                block.end_time.format("%H:%M"),
+                escape_compact(block.name.as_deref().unwrap_or("Unnamed item"))
+            )
+        })
+        .collect()
+}
+
+/// Prints the schedule, grouped into a section per context. Section headers use the context's
+/// display color. When a limit is given, only the first `limit` blocks are printed, followed by a
+/// count of the blocks left out.
+pub fn print_schedule(schedule: Vec<TimeBlock>, limit: Option<usize>, contexts: &[Context]) {
+    print!("{}", render_schedule(schedule, limit, contexts));
+}
+
+/// The text `print_schedule` prints.
+pub fn render_schedule(
+    schedule: Vec<TimeBlock>,
+    limit: Option<usize>,
+    contexts: &[Context],
+) -> String {
+    let total_blocks = schedule.len();
+    let shown_blocks = limit.unwrap_or(total_blocks).min(total_blocks);
+    let mut current_section: Option<String> = None;
+    let mut output = String::new();
+
+    for block in schedule.into_iter().take(shown_blocks) {
+        if block.context.is_some() && block.context != current_section {
+            let name = block.context.clone().unwrap();
+            let header = match contexts
+                .iter()
+                .find(|context| name_key(&context.name) == name_key(&name))
+            {
+                Some(context) => context.colorize(&name),
+                None => name,
+            };
+            output.push_str(&format!("== {} ==\n", header));
+            current_section = block.context.clone();
+        }
+
+        output.push_str(&format!(
+            "{start} - {end} | {block_name}\n",
+            start = block.start_time,
+            end = block.end_time,
             block_name = match block.name {
                 Some(name) => {
                     name
@@ -228,6 +1663,12 @@ pub fn print_schedule(schedule: Vec<TimeBlock>) {
                     "Unnamed item".to_string()
                 }
             }
-        )
+        ));
     }
+
+    if shown_blocks < total_blocks {
+        output.push_str(&format!("... and {} more\n", total_blocks - shown_blocks));
+    }
+
+    output
 }
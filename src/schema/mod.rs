@@ -0,0 +1,16 @@
+/// A JSON Schema for the data file, for tools that read or write it.
+///
+/// It's derived from the stored types, so it follows their serde attributes as they change.
+use schemars::SchemaGenerator;
+
+use super::model::PreemptApp;
+use super::timeblock::FuzzyTimeBlock;
+
+/// The data file's schema as pretty-printed JSON. Fuzzy time blocks aren't stored in the file
+/// yet, but their definition is included for tools that exchange them.
+pub fn data_schema() -> String {
+    let mut generator = SchemaGenerator::default();
+    generator.subschema_for::<FuzzyTimeBlock>();
+    let schema = generator.into_root_schema_for::<PreemptApp>();
+    format!("{:#}\n", schema.as_value())
+}
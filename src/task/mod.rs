@@ -1,23 +1,355 @@
-use chrono::{Duration, NaiveDateTime, Utc};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
 
 /// Utilities for manipulating tasks.
+use super::clock;
 use super::context::Context;
-use serde_with::{serde_as, DurationSeconds};
+use super::readable::{ClockTime, Minutes};
+use serde_with::serde_as;
 
 const DEFAULT_DURATION_MIN: i64 = 25;
 
+/// How durations are shown to the user.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DurationUnits {
+    Minutes,
+    /// Counts of pomodoros of the given length.
+    Pomodoros(Duration),
+}
+
+/// Formats a duration in hours and minutes, like "1h 30m", "45m" or "2h". Seconds are dropped.
+pub fn format_duration(duration: Duration) -> String {
+    if duration < Duration::zero() {
+        return format!("-{}", format_duration(-duration));
+    }
+    let hours = duration.num_hours();
+    let minutes = duration.num_minutes() % 60;
+    match (hours, minutes) {
+        (0, minutes) => format!("{}m", minutes),
+        (hours, 0) => format!("{}h", hours),
+        (hours, minutes) => format!("{}h {}m", hours, minutes),
+    }
+}
+
+/// Formats an estimate with its uncertainty, like "25m (±10m)", or just the duration when the
+/// uncertainty is unknown.
+pub fn format_estimate(duration: Duration, uncertainty: Option<Duration>) -> String {
+    match uncertainty {
+        Some(uncertainty) => format!(
+            "{} (±{})",
+            format_duration(duration),
+            format_duration(uncertainty)
+        ),
+        None => format_duration(duration),
+    }
+}
+
+/// Formats a duration like "1h 30m" or "1.5 pomodoros". Partial pomodoros are rounded up to the
+/// next half, since even a little leftover work takes a slot.
+pub fn format_duration_in(duration: Duration, units: DurationUnits) -> String {
+    match units {
+        DurationUnits::Minutes => format_duration(duration),
+        DurationUnits::Pomodoros(length) => {
+            let length = length.num_seconds().max(1);
+            let halves = (duration.num_seconds() * 2 + length - 1) / length;
+            let count = if halves % 2 == 0 {
+                format!("{}", halves / 2)
+            } else {
+                format!("{}.5", halves / 2)
+            };
+            if halves == 2 {
+                String::from("1 pomodoro")
+            } else {
+                format!("{} pomodoros", count)
+            }
+        }
+    }
+}
+
+/// How firm a task's deadline is.
+#[derive(
+    serde::Serialize, serde::Deserialize, schemars::JsonSchema, Clone, Copy, Debug, Eq, PartialEq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadlineKind {
+    /// Nice to finish by. Missing it is a warning.
+    Soft,
+    /// Must finish by. Missing it is an error, and fails `--strict`.
+    Hard,
+}
+
+impl std::fmt::Display for DeadlineKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DeadlineKind::Soft => write!(f, "soft"),
+            DeadlineKind::Hard => write!(f, "hard"),
+        }
+    }
+}
+
+/// How demanding a task is. High difficulty work is saved for high energy hours.
+#[derive(
+    serde::Serialize, serde::Deserialize, schemars::JsonSchema, Clone, Copy, Debug, Eq, PartialEq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Low,
+    Medium,
+    High,
+}
+
+impl std::fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Difficulty::Low => write!(f, "low"),
+            Difficulty::Medium => write!(f, "medium"),
+            Difficulty::High => write!(f, "high"),
+        }
+    }
+}
+
+/// When a task comes back, done or not, like a daily habit.
+#[derive(
+    serde::Serialize, serde::Deserialize, schemars::JsonSchema, Clone, Copy, Debug, Eq, PartialEq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ResetSchedule {
+    /// Every day at midnight.
+    Daily,
+    /// At midnight as the given day starts.
+    Weekly(Weekday),
+}
+
+impl ResetSchedule {
+    /// The most recent reset at or before `at`.
+    pub fn last_boundary(&self, at: NaiveDateTime) -> NaiveDateTime {
+        let date = match self {
+            ResetSchedule::Daily => at.date(),
+            ResetSchedule::Weekly(day) => {
+                let back =
+                    (7 + at.weekday().num_days_from_monday() - day.num_days_from_monday()) % 7;
+                at.date() - Duration::days(back as i64)
+            }
+        };
+        date.and_time(NaiveTime::MIN)
+    }
+}
+
+impl std::fmt::Display for ResetSchedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ResetSchedule::Daily => write!(f, "daily"),
+            ResetSchedule::Weekly(day) => write!(f, "weekly on {}", day),
+        }
+    }
+}
+
+/// Trims a task or context name and collapses runs of whitespace inside it to single spaces, so
+/// "  Write   report " is stored as "Write report".
+pub fn normalize_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// The form names are compared in. Lookups ignore case and spacing.
+pub fn name_key(name: &str) -> String {
+    normalize_name(name).to_lowercase()
+}
+
+/// How many columns of the task list the name gets.
+const NAME_COLUMN_WIDTH: usize = 24;
+
+/// Whether the character belongs to the one before it on screen: combining marks, variation
+/// selectors, emoji skin tones and tag characters.
+fn is_extending(c: char) -> bool {
+    matches!(c as u32,
+        0x0300..=0x036F
+        | 0x1AB0..=0x1AFF
+        | 0x1DC0..=0x1DFF
+        | 0x200C
+        | 0x20D0..=0x20FF
+        | 0xFE00..=0xFE0F
+        | 0xFE20..=0xFE2F
+        | 0x1F3FB..=0x1F3FF
+        | 0xE0020..=0xE007F
+        | 0xE0100..=0xE01EF)
+}
+
+/// How many terminal columns the character takes. CJK and emoji are two wide.
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+/// Splits text into the units a reader sees as one character, so a truncation never separates
+/// an accent from its letter or breaks up an emoji sequence joined with zero width joiners.
+fn clusters(text: &str) -> Vec<&str> {
+    let mut clusters = vec![];
+    let mut start = 0;
+    let mut joined = false;
+    for (i, c) in text.char_indices() {
+        if i > start && !joined && !is_extending(c) && c != '\u{200D}' {
+            clusters.push(&text[start..i]);
+            start = i;
+        }
+        joined = c == '\u{200D}';
+    }
+    if start < text.len() {
+        clusters.push(&text[start..]);
+    }
+    clusters
+}
+
+/// Fits text to exactly `width` columns, padding it with spaces or cutting it short with an
+/// ellipsis. Cuts fall between whole characters, and wide characters count as two columns.
+pub fn fit_column(text: &str, width: usize) -> String {
+    let parts = clusters(text);
+    let widths: Vec<usize> = parts
+        .iter()
+        .map(|part| part.chars().next().map_or(0, char_width))
+        .collect();
+
+    let mut fitted = String::new();
+    let mut used = 0;
+    if widths.iter().sum::<usize>() > width {
+        for (part, part_width) in parts.iter().zip(&widths) {
+            if used + part_width + 1 > width {
+                break;
+            }
+            fitted.push_str(part);
+            used += part_width;
+        }
+        if width > 0 {
+            fitted.push('…');
+            used += 1;
+        }
+    } else {
+        fitted.push_str(text);
+        used = widths.iter().sum();
+    }
+    fitted.push_str(&" ".repeat(width - used));
+    fitted
+}
+
+/// Describes how long ago `dt` was, like "5 minutes ago" or "3 days ago", in the largest whole
+/// unit up to weeks. Anything under a minute, or in the future, is "just now".
+pub fn humanize_since(dt: NaiveDateTime, now: NaiveDateTime) -> String {
+    let elapsed = now - dt;
+    let (count, unit) = if elapsed.num_weeks() > 0 {
+        (elapsed.num_weeks(), "week")
+    } else if elapsed.num_days() > 0 {
+        (elapsed.num_days(), "day")
+    } else if elapsed.num_hours() > 0 {
+        (elapsed.num_hours(), "hour")
+    } else if elapsed.num_minutes() > 0 {
+        (elapsed.num_minutes(), "minute")
+    } else {
+        return String::from("just now");
+    };
+    if count == 1 {
+        format!("1 {} ago", unit)
+    } else {
+        format!("{} {}s ago", count, unit)
+    }
+}
+
+/// The contexts of a task as stored: a list, or a single name or null from files written before
+/// tasks could have more than one.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum StoredContexts {
+    Many(Vec<String>),
+    One(Option<String>),
+}
+
+fn deserialize_contexts<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    Ok(
+        match <StoredContexts as serde::Deserialize>::deserialize(deserializer)? {
+            StoredContexts::Many(contexts) => contexts,
+            StoredContexts::One(context) => context.into_iter().collect(),
+        },
+    )
+}
+
 /// A description of a thing to do.
 #[serde_as]
-#[derive(serde::Serialize, serde::Deserialize, Clone)]
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema, Clone)]
 pub struct Task {
     pub name: String,
     description: String,
     pub priority: i32,
     done: bool,
-    #[serde_as(as = "DurationSeconds<i64>")]
+    #[serde_as(as = "Minutes")]
+    #[schemars(with = "Minutes")]
     duration: Duration,
-    context: Option<String>,
+    /// The contexts the task can be done in, most preferred first. It's scheduled in the first of
+    /// them that's active and has room.
+    #[serde(default, alias = "context", deserialize_with = "deserialize_contexts")]
+    contexts: Vec<String>,
     created: NaiveDateTime,
+    /// Archived tasks are kept for history but hidden from listings and never scheduled.
+    #[serde(default)]
+    archived: bool,
+    deadline: Option<NaiveDate>,
+    /// Deadlines without a kind are soft.
+    #[serde(default)]
+    deadline_kind: Option<DeadlineKind>,
+    /// Names of the tasks that must be finished before this one.
+    #[serde(default)]
+    depends_on: Vec<String>,
+    /// A time the task must start at. Pinned tasks are placed first and others fit around them.
+    #[serde(default)]
+    #[serde_as(as = "Option<ClockTime>")]
+    #[schemars(with = "Option<ClockTime>")]
+    fixed_start: Option<NaiveTime>,
+    /// The shortest stretch of time worth starting the task in. It waits for a longer gap
+    /// otherwise.
+    #[serde_as(as = "Option<Minutes>")]
+    #[schemars(with = "Option<Minutes>")]
+    #[serde(default)]
+    min_block: Option<Duration>,
+    /// Tasks without a difficulty are medium.
+    #[serde(default)]
+    difficulty: Option<Difficulty>,
+    /// A manual place in the task's priority class. Lower orders go first, ahead of any task
+    /// without one.
+    #[serde(default)]
+    order: Option<i32>,
+    /// The task is left out of schedules before this date.
+    #[serde(default)]
+    snoozed_until: Option<NaiveDate>,
+    /// How far the duration could be off either way.
+    #[serde_as(as = "Option<Minutes>")]
+    #[schemars(with = "Option<Minutes>")]
+    #[serde(default)]
+    estimate_uncertainty: Option<Duration>,
+    /// Brings the task back on a schedule, undone and with its full duration, whether or not it
+    /// was finished.
+    #[serde(default)]
+    reset: Option<ResetSchedule>,
+    /// The duration the task goes back to when it resets.
+    #[serde_as(as = "Option<Minutes>")]
+    #[schemars(with = "Option<Minutes>")]
+    #[serde(default)]
+    reset_duration: Option<Duration>,
+    /// When the task last reset. Resets are counted from its creation until then.
+    #[serde(default)]
+    last_reset: Option<NaiveDateTime>,
 }
 
 impl Task {
@@ -29,13 +361,29 @@ impl Task {
         context: Option<String>,
     ) -> Task {
         Task {
-            name,
+            name: normalize_name(&name),
             description,
             priority,
             done,
             duration: Duration::minutes(DEFAULT_DURATION_MIN),
-            context,
-            created: Utc::now().naive_utc(),
+            contexts: context
+                .iter()
+                .map(|context| normalize_name(context))
+                .collect(),
+            created: clock::now(),
+            archived: false,
+            deadline: None,
+            deadline_kind: None,
+            depends_on: vec![],
+            fixed_start: None,
+            min_block: None,
+            difficulty: None,
+            order: None,
+            snoozed_until: None,
+            estimate_uncertainty: None,
+            reset: None,
+            reset_duration: None,
+            last_reset: None,
         }
     }
 
@@ -48,40 +396,378 @@ impl Task {
         context: Option<String>,
     ) -> Task {
         Task {
-            name,
+            name: normalize_name(&name),
             description,
             priority,
             done,
             duration,
-            context,
-            created: Utc::now().naive_utc(),
+            contexts: context
+                .iter()
+                .map(|context| normalize_name(context))
+                .collect(),
+            created: clock::now(),
+            archived: false,
+            deadline: None,
+            deadline_kind: None,
+            depends_on: vec![],
+            fixed_start: None,
+            min_block: None,
+            difficulty: None,
+            order: None,
+            snoozed_until: None,
+            estimate_uncertainty: None,
+            reset: None,
+            reset_duration: None,
+            last_reset: None,
         }
     }
 
-    pub fn filter_context_tasks(context: &Context, tasks: Vec<Task>) -> Vec<Task> {
-        let mut filtered_tasks: Vec<Task> = vec![];
+    /// The open tasks to schedule in `context`. A task listing several contexts waits while
+    /// `pending` says one it prefers is still to be scheduled, so it lands in the first with room.
+    pub fn filter_context_tasks(
+        context: &Context,
+        tasks: Vec<Task>,
+        pending: impl Fn(&str) -> bool,
+    ) -> Vec<Task> {
+        tasks
+            .into_iter()
+            .filter(|task| {
+                task.is_schedulable()
+                    && task
+                        .context_position(&context.name)
+                        .is_some_and(|position| {
+                            !task.contexts[..position].iter().any(|name| pending(name))
+                        })
+            })
+            .collect()
+    }
+
+    /// Where the named context comes in the task's preferences, if it's listed at all.
+    fn context_position(&self, context_name: &str) -> Option<usize> {
+        self.contexts
+            .iter()
+            .position(|name| name_key(name) == name_key(context_name))
+    }
+
+    /// Whether the task can be done in the named context. Names are compared case-insensitively,
+    /// and a task without a context matches nothing.
+    pub fn is_context_match(&self, context_name: &str) -> bool {
+        self.context_position(context_name).is_some()
+    }
 
-        for task in tasks {
-            if task.context.is_some()
-                && !task.done
-                && (task.clone().context.unwrap().to_lowercase() == context.name.to_lowercase())
-            {
-                filtered_tasks.push(task);
+    /// Whether the task still wants scheduling, i.e. it's neither done nor archived.
+    pub fn is_schedulable(&self) -> bool {
+        !self.done && !self.archived
+    }
+
+    pub fn estimate_uncertainty(&self) -> Option<Duration> {
+        self.estimate_uncertainty
+    }
+
+    pub fn set_estimate_uncertainty(&mut self, estimate_uncertainty: Option<Duration>) {
+        self.estimate_uncertainty = estimate_uncertainty;
+    }
+
+    pub fn snoozed_until(&self) -> Option<NaiveDate> {
+        self.snoozed_until
+    }
+
+    pub fn set_snoozed_until(&mut self, snoozed_until: Option<NaiveDate>) {
+        self.snoozed_until = snoozed_until;
+    }
+
+    /// Whether the task is snoozed on `date`. It comes back on the date it was snoozed until.
+    pub fn is_snoozed_on(&self, date: NaiveDate) -> bool {
+        self.snoozed_until.is_some_and(|until| date < until)
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// The task's preferred context.
+    pub fn context(&self) -> Option<&str> {
+        self.contexts.first().map(String::as_str)
+    }
+
+    pub fn contexts(&self) -> &[String] {
+        &self.contexts
+    }
+
+    /// Replaces the task's contexts, most preferred first.
+    pub fn set_contexts(&mut self, contexts: Vec<String>) {
+        self.contexts = contexts
+            .iter()
+            .map(|context| normalize_name(context))
+            .collect();
+    }
+
+    /// The contexts joined for display.
+    fn contexts_label(&self) -> String {
+        if self.contexts.is_empty() {
+            String::from("No context")
+        } else {
+            self.contexts.join(", ")
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    pub fn is_archived(&self) -> bool {
+        self.archived
+    }
+
+    pub fn deadline(&self) -> Option<NaiveDate> {
+        self.deadline
+    }
+
+    pub fn set_deadline(&mut self, deadline: Option<NaiveDate>) {
+        self.deadline = deadline;
+    }
+
+    /// How firm the deadline is, soft unless set otherwise.
+    pub fn deadline_kind(&self) -> DeadlineKind {
+        self.deadline_kind.unwrap_or(DeadlineKind::Soft)
+    }
+
+    pub fn set_deadline_kind(&mut self, deadline_kind: Option<DeadlineKind>) {
+        self.deadline_kind = deadline_kind;
+    }
+
+    pub fn fixed_start(&self) -> Option<NaiveTime> {
+        self.fixed_start
+    }
+
+    pub fn set_fixed_start(&mut self, fixed_start: Option<NaiveTime>) {
+        self.fixed_start = fixed_start;
+    }
+
+    pub fn min_block(&self) -> Option<Duration> {
+        self.min_block
+    }
+
+    pub fn set_min_block(&mut self, min_block: Option<Duration>) {
+        self.min_block = min_block;
+    }
+
+    /// How demanding the task is, medium unless set otherwise.
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty.unwrap_or(Difficulty::Medium)
+    }
+
+    pub fn set_difficulty(&mut self, difficulty: Option<Difficulty>) {
+        self.difficulty = difficulty;
+    }
+
+    pub fn reset(&self) -> Option<ResetSchedule> {
+        self.reset
+    }
+
+    /// Sets when the task resets. It resets to its current duration.
+    pub fn set_reset(&mut self, reset: Option<ResetSchedule>) {
+        self.reset = reset;
+        self.reset_duration = reset.map(|_| self.duration);
+    }
+
+    /// Marks the task undone and restores its duration if a reset has come round since it last
+    /// reset, or since it was created. Returns whether it reset.
+    pub fn reset_if_due(&mut self, now: NaiveDateTime) -> bool {
+        let boundary = match self.reset {
+            Some(reset) => reset.last_boundary(now),
+            None => return false,
+        };
+        if self.last_reset.unwrap_or(self.created) >= boundary {
+            return false;
+        }
+        self.done = false;
+        if let Some(duration) = self.reset_duration {
+            self.duration = duration;
+        }
+        self.last_reset = Some(boundary);
+        true
+    }
+
+    pub fn order(&self) -> Option<i32> {
+        self.order
+    }
+
+    pub fn set_order(&mut self, order: Option<i32>) {
+        self.order = order;
+    }
+
+    /// Whether the task may start in a gap of this length.
+    pub fn fits_in(&self, available: Duration) -> bool {
+        self.min_block.is_none_or(|min| min <= available)
+    }
+
+    pub fn depends_on(&self) -> &[String] {
+        &self.depends_on
+    }
+
+    pub fn add_dependency(&mut self, name: &str) {
+        if !self
+            .depends_on
+            .iter()
+            .any(|dep| name_key(dep) == name_key(name))
+        {
+            self.depends_on.push(normalize_name(name));
+        }
+    }
+
+    pub fn created(&self) -> NaiveDateTime {
+        self.created
+    }
+
+    /// How long the task has existed at the given time.
+    pub fn age_at(&self, at: NaiveDateTime) -> Duration {
+        at - self.created
+    }
+
+    pub fn complete(&mut self) {
+        self.done = true;
+    }
+
+    pub fn archive(&mut self) {
+        self.archived = true;
+    }
+
+    /// Prints a one line summary of the task.
+    pub fn print_summary(&self) {
+        self.print_summary_in(DurationUnits::Minutes);
+    }
+
+    /// Prints a one line summary of the task, with its duration in the given units.
+    pub fn print_summary_in(&self, units: DurationUnits) {
+        println!("{}", self.summary_in(units));
+    }
+
+    /// The one line summary of the task, with its duration in the given units.
+    pub fn summary_in(&self, units: DurationUnits) -> String {
+        format!(
+            "{name} | priority {priority} | {duration} | {context}{deadline}{status}",
+            name = fit_column(&self.name, NAME_COLUMN_WIDTH),
+            priority = self.priority,
+            duration = format_duration_in(self.duration, units),
+            context = self.contexts_label(),
+            deadline = match self.deadline {
+                Some(deadline) => format!(" | due {}", deadline),
+                None => String::new(),
+            },
+            status = if self.archived {
+                " | archived"
+            } else if self.done {
+                " | done"
+            } else {
+                ""
             }
+        )
+    }
+
+    /// The details of the task, one per line, with its age relative to `now`.
+    pub fn render(&self, now: NaiveDateTime, units: DurationUnits) -> String {
+        let mut lines = vec![];
+        lines.push(format!("Task - {}", self.name));
+        if !self.description.is_empty() {
+            lines.push(format!("- Description: {}", self.description));
+        }
+        lines.push(format!("- Priority: {}", self.priority));
+        match self.estimate_uncertainty {
+            Some(uncertainty) => lines.push(format!(
+                "- Duration: {} (±{})",
+                format_duration_in(self.duration, units),
+                format_duration_in(uncertainty, units)
+            )),
+            None => lines.push(format!(
+                "- Duration: {}",
+                format_duration_in(self.duration, units)
+            )),
+        }
+        lines.push(format!("- Context: {}", self.contexts_label()));
+        if let Some(deadline) = self.deadline {
+            lines.push(format!("- Due: {} ({})", deadline, self.deadline_kind()));
+        }
+        if let Some(fixed_start) = self.fixed_start {
+            lines.push(format!("- Pinned at: {}", fixed_start.format("%H:%M")));
+        }
+        if let Some(min_block) = self.min_block {
+            lines.push(format!(
+                "- Minimum block: {}",
+                format_duration_in(min_block, units)
+            ));
+        }
+        if let Some(snoozed_until) = self.snoozed_until {
+            lines.push(format!("- Snoozed until: {}", snoozed_until));
+        }
+        if let Some(order) = self.order {
+            lines.push(format!("- Order: {}", order));
+        }
+        if let Some(difficulty) = self.difficulty {
+            lines.push(format!("- Difficulty: {}", difficulty));
+        }
+        if let Some(reset) = self.reset {
+            lines.push(format!("- Resets: {}", reset));
         }
+        if !self.depends_on.is_empty() {
+            lines.push(format!("- Depends on: {}", self.depends_on.join(", ")));
+        }
+        if self.archived {
+            lines.push("- Status: archived".to_string());
+        } else if self.done {
+            lines.push("- Status: done".to_string());
+        }
+        lines.push(format!(
+            "- Created: {} ({})",
+            humanize_since(self.created, now),
+            self.created.format("%Y-%m-%d %H:%M")
+        ));
+        lines.join("\n")
+    }
+
+    /// Prints the details of the task, with its age relative to `now`.
+    pub fn print(&self, now: NaiveDateTime, units: DurationUnits) {
+        println!("{}", self.render(now, units));
+    }
 
-        filtered_tasks
+    /// Splits the task into `n` roughly equal pieces of its remaining duration, named like
+    /// "Write report (1/3)". Each piece keeps the task's other properties.
+    pub fn split(self, n: usize) -> Vec<Task> {
+        let n = n.max(1) as i64;
+        let total_seconds = self.duration.num_seconds();
+
+        (0..n)
+            .map(|i| {
+                let mut piece = self.clone();
+                piece.name = format!("{} ({}/{})", self.name, i + 1, n);
+                // Spread any remainder over the first pieces.
+                let extra = if i < total_seconds % n { 1 } else { 0 };
+                piece.duration = Duration::seconds(total_seconds / n + extra);
+                piece
+            })
+            .collect()
+    }
+
+    /// Folds a duplicate of this task into it. The duplicate's duration is added on, and contexts
+    /// it lists that this task doesn't are added as fallbacks.
+    pub fn merge(&mut self, duplicate: &Task) {
+        self.duration += duplicate.duration;
+        for context in &duplicate.contexts {
+            if self.context_position(context).is_none() {
+                self.contexts.push(context.clone());
+            }
+        }
     }
 
     pub fn do_work(&mut self, duration: Duration) {
         if self.duration < duration {
             self.duration = Duration::minutes(0);
         } else {
-            self.duration = self.duration - duration;
+            self.duration -= duration;
         }
     }
 
-    pub fn has_work_remaining(&mut self) -> bool {
-        return self.duration > Duration::minutes(0);
+    pub fn has_work_remaining(&self) -> bool {
+        self.duration > Duration::minutes(0)
     }
 }
@@ -1,18 +1,28 @@
 /// Various forms of scheduling.
-use chrono::{Duration, NaiveDate, NaiveTime, Weekday};
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
 
 use super::location::GeoFence;
+use super::readable::{ClockTime, Minutes};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use serde_with::{serde_as, DurationSeconds};
+use serde_with::serde_as;
 
 /// A concrete block of time. Used for immovable/unschedulable schedule items and scheduler outputs.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde_as]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
 pub struct TimeBlock {
     pub name: Option<String>,
+    #[serde_as(as = "ClockTime")]
+    #[schemars(with = "ClockTime")]
     pub start_time: NaiveTime,
+    #[serde_as(as = "ClockTime")]
+    #[schemars(with = "ClockTime")]
     pub end_time: NaiveTime,
     pub start_date: NaiveDate,
     pub end_date: NaiveDate,
+    /// The name of the context this block was scheduled in, if any.
+    #[serde(default)]
+    pub context: Option<String>,
 }
 
 impl TimeBlock {
@@ -28,6 +38,7 @@ impl TimeBlock {
             end_time,
             start_date,
             end_date,
+            context: None,
         }
     }
 
@@ -44,24 +55,52 @@ impl TimeBlock {
             end_time,
             start_date,
             end_date,
+            context: None,
         }
     }
 
-    /// TODO: Just say everything intersects for now.
-    pub fn intersects(&self, other: TimeBlock) -> bool {
-        true
+    /// The length of the block, spanning dates if the block does.
+    pub fn duration(&self) -> Duration {
+        self.end_date.and_time(self.end_time) - self.start_date.and_time(self.start_time)
+    }
+
+    /// Whether the block covers the moment, counting its start but not its end. A block whose end
+    /// is at or before its start on the same date is taken to run past midnight.
+    pub fn contains(&self, dt: NaiveDateTime) -> bool {
+        let start = self.start_date.and_time(self.start_time);
+        let mut end = self.end_date.and_time(self.end_time);
+        if end <= start && self.start_date == self.end_date {
+            end += Duration::days(1);
+        }
+        start <= dt && dt < end
+    }
+
+    /// Whether the two blocks share any time. Blocks that only touch end to start don't intersect.
+    pub fn intersects(&self, other: &TimeBlock) -> bool {
+        let start = self.start_date.and_time(self.start_time);
+        let end = self.end_date.and_time(self.end_time);
+        let other_start = other.start_date.and_time(other.start_time);
+        let other_end = other.end_date.and_time(other.end_time);
+
+        start < other_end && other_start < end
     }
 }
 
 /// A (potentially recurring) event with fuzzy planning.
 #[serde_as]
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct FuzzyTimeBlock {
+    #[serde_as(as = "ClockTime")]
+    #[schemars(with = "ClockTime")]
     start_time: NaiveTime,
-    #[serde_as(as = "DurationSeconds<i64>")]
+    #[serde_as(as = "Minutes")]
+    #[schemars(with = "Minutes")]
     start_uncertainty: Duration,
+    #[serde_as(as = "ClockTime")]
+    #[schemars(with = "ClockTime")]
     end_time: NaiveTime,
-    #[serde_as(as = "DurationSeconds<i64>")]
+    #[serde_as(as = "Minutes")]
+    #[schemars(with = "Minutes")]
     end_uncertainty: Duration,
     place: Option<GeoFence>,
     weekdays: Option<Vec<Weekday>>,
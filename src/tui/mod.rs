@@ -0,0 +1,197 @@
+/// A scrollable view of a day's timeline, for `preempt tui`.
+///
+/// The view is kept apart from the terminal: it takes keys and renders frames as text, so it can
+/// be driven and checked without touching the terminal's mode. `run` puts it on the terminal.
+use std::io;
+
+use chrono::NaiveDateTime;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, Widget};
+
+use super::timeblock::TimeBlock;
+
+/// The keys the view responds to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Up,
+    Down,
+    Quit,
+    Other,
+}
+
+impl Key {
+    /// Reads a key pressed in the terminal. Ctrl-C and Esc quit, since raw mode keeps Ctrl-C
+    /// from interrupting.
+    pub fn from_event(event: KeyEvent) -> Key {
+        match event.code {
+            KeyCode::Up | KeyCode::Char('k') => Key::Up,
+            KeyCode::Down | KeyCode::Char('j') => Key::Down,
+            KeyCode::Char('c') if event.modifiers.contains(KeyModifiers::CONTROL) => Key::Quit,
+            KeyCode::Char('q') | KeyCode::Esc => Key::Quit,
+            _ => Key::Other,
+        }
+    }
+}
+
+/// The state of the timeline view: the blocks, which one is in progress and how far it's scrolled.
+#[derive(Debug, Clone)]
+pub struct TimelineView {
+    blocks: Vec<TimeBlock>,
+    current: Option<usize>,
+    offset: usize,
+    height: usize,
+}
+
+impl TimelineView {
+    /// A view showing `height` blocks at a time, scrolled so the block in progress at `now` is the
+    /// first one shown.
+    pub fn new(blocks: Vec<TimeBlock>, now: NaiveDateTime, height: usize) -> TimelineView {
+        let current = blocks.iter().position(|block| block.contains(now));
+        let mut view = TimelineView {
+            blocks,
+            current,
+            offset: 0,
+            height: height.max(1),
+        };
+        view.offset = current.unwrap_or(0).min(view.max_offset());
+        view
+    }
+
+    /// The index of the block in progress, if any.
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    /// The index of the first block shown.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Shows `height` blocks at a time from now on, scrolling back if the end would be left blank.
+    pub fn set_height(&mut self, height: usize) {
+        self.height = height.max(1);
+        self.offset = self.offset.min(self.max_offset());
+    }
+
+    fn end(&self) -> usize {
+        (self.offset + self.height).min(self.blocks.len())
+    }
+
+    fn max_offset(&self) -> usize {
+        self.blocks.len().saturating_sub(self.height)
+    }
+
+    /// Applies a key, returning false once the view should close.
+    pub fn handle(&mut self, key: Key) -> bool {
+        match key {
+            Key::Up => self.offset = self.offset.saturating_sub(1),
+            Key::Down => self.offset = (self.offset + 1).min(self.max_offset()),
+            Key::Quit => return false,
+            Key::Other => {}
+        }
+        true
+    }
+
+    /// The frame for the view as it stands, with the block in progress marked by `>`.
+    pub fn render(&self) -> String {
+        if self.blocks.is_empty() {
+            return String::from("Nothing scheduled today.\n[q] quit\n");
+        }
+
+        let end = self.end();
+        let mut output = String::new();
+        for (i, block) in self.blocks[self.offset..end].iter().enumerate() {
+            let marker = if Some(self.offset + i) == self.current {
+                '>'
+            } else {
+                ' '
+            };
+            output.push_str(&format!(
+                "{} {} - {} | {}\n",
+                marker,
+                block.start_time.format("%H:%M"),
+                block.end_time.format("%H:%M"),
+                block.name.as_deref().unwrap_or("Unnamed item")
+            ));
+        }
+        output.push_str(&format!(
+            "Blocks {}-{} of {}  [up/down] scroll  [q] quit\n",
+            self.offset + 1,
+            end,
+            self.blocks.len()
+        ));
+        output
+    }
+}
+
+/// Draws the view as a list in a bordered frame, with the block in progress highlighted.
+impl Widget for &TimelineView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let frame = Block::bordered().title(" Today ");
+        if self.blocks.is_empty() {
+            List::new([ListItem::new("Nothing scheduled today.")])
+                .block(frame.title_bottom(" [q] quit "))
+                .render(area, buf);
+            return;
+        }
+
+        let end = self.end();
+        let items = self.blocks[self.offset..end]
+            .iter()
+            .enumerate()
+            .map(|(i, block)| {
+                let line = format!(
+                    "{} - {} | {}",
+                    block.start_time.format("%H:%M"),
+                    block.end_time.format("%H:%M"),
+                    block.name.as_deref().unwrap_or("Unnamed item")
+                );
+                if Some(self.offset + i) == self.current {
+                    ListItem::new(format!("> {}", line))
+                        .style(Style::new().add_modifier(Modifier::REVERSED))
+                } else {
+                    ListItem::new(format!("  {}", line))
+                }
+            });
+        let footer = Line::from(format!(
+            " Blocks {}-{} of {}  [up/down] scroll  [q] quit ",
+            self.offset + 1,
+            end,
+            self.blocks.len()
+        ));
+        List::new(items)
+            .block(frame.title_bottom(footer))
+            .render(area, buf);
+    }
+}
+
+/// Shows the view on the terminal until it's closed, reading keys as they're pressed. The view is
+/// sized to fit inside the frame's borders on every draw, so it follows the terminal as it's
+/// resized. The terminal is put back the way it was afterwards, even if drawing fails.
+pub fn run(mut view: TimelineView) -> io::Result<()> {
+    let mut terminal = ratatui::try_init()?;
+    let result = loop {
+        let drawn = terminal.draw(|frame| {
+            view.set_height(frame.area().height.saturating_sub(2) as usize);
+            frame.render_widget(&view, frame.area());
+        });
+        if let Err(error) = drawn {
+            break Err(error);
+        }
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => {
+                if !view.handle(Key::from_event(key)) {
+                    break Ok(());
+                }
+            }
+            Ok(_) => {}
+            Err(error) => break Err(error),
+        }
+    };
+    ratatui::restore();
+    result
+}
@@ -0,0 +1,2444 @@
+//! End-to-end runs of the preempt binary against a throwaway data directory.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+/// A Monday, so contexts active on weekdays are scheduled.
+const NOW: &str = "2024-06-03T12:00:00Z";
+
+/// A fresh data directory, removed again when the test ends.
+struct TestDir(PathBuf);
+
+impl TestDir {
+    fn new(name: &str) -> TestDir {
+        let dir = std::env::temp_dir().join(format!("preempt-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        TestDir(dir)
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+
+    fn data(&self) -> String {
+        fs::read_to_string(self.0.join("preempt_data.yaml")).unwrap()
+    }
+
+    /// Runs preempt with the clock frozen and the config file pointed inside the test
+    /// directory, so neither the real clock nor the user's settings leak in.
+    fn run(&self, args: &[&str]) -> Output {
+        self.command(args).output().unwrap()
+    }
+
+    /// Like `run`, with `input` piped to stdin.
+    fn run_with_stdin(&self, args: &[&str], input: &str) -> Output {
+        let mut child = self
+            .command(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(input.as_bytes())
+            .unwrap();
+        child.wait_with_output().unwrap()
+    }
+
+    fn command(&self, args: &[&str]) -> Command {
+        self.command_at(NOW, args)
+    }
+
+    /// Like `command`, with the clock frozen at `now` instead.
+    fn command_at(&self, now: &str, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_preempt"));
+        command
+            .arg("--data-dir")
+            .arg(self.path())
+            .args(["--now", now])
+            .args(args)
+            .env("XDG_CONFIG_HOME", self.path().join("config"))
+            .env_remove("PREEMPT_DATA_DIR");
+        command
+    }
+
+    /// Runs preempt and returns its output, failing the test if it didn't succeed.
+    fn run_ok(&self, args: &[&str]) -> String {
+        let output = self.run(args);
+        assert!(
+            output.status.success(),
+            "preempt {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    fn add_work_context(&self) {
+        self.run_ok(&[
+            "add-context",
+            "--name",
+            "Work",
+            "--days",
+            "Mon-Fri",
+            "--start",
+            "09:00",
+            "--end",
+            "10:00",
+        ]);
+    }
+
+    /// Adds a task to the Work context.
+    fn add_work_task(&self, name: &str, duration: &str, priority: &str) {
+        self.run_ok(&[
+            "add-task",
+            "--name",
+            name,
+            "--context",
+            "Work",
+            "--duration",
+            duration,
+            "--priority",
+            priority,
+        ]);
+    }
+}
+
+/// The task names in a timeline, one per work block.
+fn scheduled_tasks(timeline: &str) -> Vec<&str> {
+    timeline
+        .lines()
+        .filter_map(|line| line.split("| Task - ").nth(1))
+        .collect()
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn add_task_saves_it() {
+    let dir = TestDir::new("add");
+    dir.add_work_context();
+
+    let output = dir.run_ok(&[
+        "add-task",
+        "--name",
+        "Write report",
+        "--duration",
+        "50",
+        "--context",
+        "Work",
+    ]);
+
+    assert_eq!(output, "Added task 'Write report'\n");
+    let data = dir.data();
+    assert!(data.contains("name: Write report"), "{}", data);
+    assert!(data.contains("duration: 50m"), "{}", data);
+    assert!(data.contains("- Work"), "{}", data);
+}
+
+#[test]
+fn add_task_with_unknown_context_saves_nothing() {
+    let dir = TestDir::new("unknown-context");
+    dir.add_work_context();
+    let before = dir.data();
+
+    let output = dir.run(&["add-task", "--name", "Email", "--context", "Home"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(dir.data(), before);
+}
+
+#[test]
+fn list_tasks_shows_added_tasks() {
+    let dir = TestDir::new("list");
+    dir.add_work_context();
+    dir.run_ok(&["add-task", "--name", "Email", "--context", "Work"]);
+    dir.run_ok(&[
+        "add-task",
+        "--name",
+        "Write report",
+        "--duration",
+        "50",
+        "--context",
+        "Work",
+        "--priority",
+        "8",
+    ]);
+
+    let output = dir.run_ok(&["list-tasks"]);
+
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", output);
+    assert!(lines[0].starts_with("Email "), "{}", output);
+    assert!(lines[1].starts_with("Write report "), "{}", output);
+    assert!(lines[1].contains("priority 8"), "{}", output);
+    assert!(lines[1].contains("50m"), "{}", output);
+}
+
+#[test]
+fn timeline_schedules_added_task() {
+    let dir = TestDir::new("timeline");
+    dir.add_work_context();
+    dir.run_ok(&[
+        "add-task",
+        "--name",
+        "Write report",
+        "--duration",
+        "50",
+        "--context",
+        "Work",
+    ]);
+
+    let output = dir.run_ok(&["timeline"]);
+
+    assert_eq!(
+        output,
+        "== Work ==\n\
+         09:00:00 - 09:25:00 | Task - Write report\n\
+         09:25:00 - 09:30:00 | Break (5 minutes)\n\
+         09:30:00 - 09:55:00 | Task - Write report\n\
+         09:55:00 - 10:00:00 | Break (5 minutes)\n\
+         Plan ends at 10:00 with 50m of work and 10m of breaks.\n"
+    );
+}
+
+#[test]
+fn timeline_save_keeps_schedule() {
+    let dir = TestDir::new("timeline-save");
+    dir.add_work_context();
+    dir.run_ok(&["add-task", "--name", "Email", "--context", "Work"]);
+
+    let timeline = dir.run_ok(&["timeline", "--save"]);
+    let saved = dir.run_ok(&["show-schedule"]);
+
+    assert!(dir.data().contains("saved_schedules"), "{}", dir.data());
+    assert!(saved.contains("Task - Email"), "{}", saved);
+    assert!(timeline.contains("Task - Email"), "{}", timeline);
+}
+
+#[test]
+fn skip_transition_break_goes_straight_to_low_work() {
+    let dir = TestDir::new("transition-break");
+    dir.add_work_context();
+    dir.run_ok(&[
+        "add-task",
+        "--name",
+        "Medium",
+        "--context",
+        "Work",
+        "--duration",
+        "25",
+        "--priority",
+        "5",
+    ]);
+    dir.run_ok(&[
+        "add-task",
+        "--name",
+        "Low",
+        "--context",
+        "Work",
+        "--duration",
+        "25",
+        "--priority",
+        "0",
+    ]);
+
+    let output = dir.run_ok(&["timeline", "--skip-transition-break"]);
+
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(
+        lines[1], "09:00:00 - 09:25:00 | Task - Medium",
+        "{}",
+        output
+    );
+    assert_eq!(lines[2], "09:25:00 - 09:50:00 | Task - Low", "{}", output);
+    assert!(
+        !lines
+            .windows(2)
+            .any(|pair| pair.iter().all(|line| line.contains("| Break"))),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn nine_to_five_preset_is_weekdays_nine_to_five() {
+    let dir = TestDir::new("preset");
+
+    dir.run_ok(&["add-context", "--name", "Work", "--preset", "nine_to_five"]);
+
+    assert_eq!(
+        dir.run_ok(&["list-contexts"]),
+        "Work | Mon, Tue, Wed, Thu, Fri | 09:00 to 17:00 | 0 open tasks\n"
+    );
+}
+
+#[test]
+fn add_context_warns_about_window_shorter_than_a_pomodoro() {
+    let dir = TestDir::new("short-window");
+
+    let output = dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Coffee",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "10:00",
+        "--end",
+        "10:15",
+    ]);
+
+    assert!(
+        output.contains(
+            "Warning: Context 'Coffee' has a window (10:00 to 10:15) shorter than one 25m work block"
+        ),
+        "{}",
+        output
+    );
+    assert_eq!(dir.run(&["check"]).status.code(), Some(2));
+}
+
+#[test]
+fn low_priority_round_robin_carries_across_days() {
+    let dir = TestDir::new("round-robin");
+    // Room for one block a day, Monday to Wednesday.
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Chores",
+        "--days",
+        "Mon-Wed",
+        "--start",
+        "18:00",
+        "--end",
+        "18:30",
+    ]);
+    for chore in ["Dishes", "Laundry", "Vacuum"] {
+        dir.run_ok(&[
+            "add-task",
+            "--name",
+            chore,
+            "--context",
+            "Chores",
+            "--duration",
+            "100",
+            "--priority",
+            "1",
+        ]);
+    }
+
+    let output = dir.run_ok(&["timeline", "--week"]);
+
+    let scheduled: Vec<&str> = output
+        .lines()
+        .filter_map(|line| line.split("| Task - ").nth(1))
+        .collect();
+    assert_eq!(scheduled, ["Dishes", "Laundry", "Vacuum"], "{}", output);
+}
+
+#[test]
+fn schema_is_json_with_a_tasks_array() {
+    let dir = TestDir::new("schema");
+
+    let output = dir.run_ok(&["schema"]);
+
+    let schema: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(schema["properties"]["tasks"]["type"], "array");
+    for name in [
+        "Task",
+        "Context",
+        "ContextException",
+        "TimeBlock",
+        "FuzzyTimeBlock",
+    ] {
+        assert!(schema["$defs"][name].is_object(), "missing {}", name);
+    }
+    assert!(!dir.path().join("preempt_data.yaml").exists());
+}
+
+#[test]
+fn import_csv_dedupe_merges_case_variants() {
+    let dir = TestDir::new("dedupe");
+    dir.add_work_context();
+    let csv = dir.path().join("tasks.csv");
+    fs::write(
+        &csv,
+        "name,duration,priority,context,description\n\
+         Email,25,1,Work,\n\
+         email,20,1,,\n",
+    )
+    .unwrap();
+
+    let output = dir.run_ok(&["import-csv", "--file", csv.to_str().unwrap(), "--dedupe"]);
+
+    assert!(output.contains("Imported 1 tasks"), "{}", output);
+    assert!(output.contains("Merged 1 duplicates"), "{}", output);
+    let tasks = dir.run_ok(&["list-tasks"]);
+    assert_eq!(tasks.lines().count(), 1, "{}", tasks);
+    assert!(tasks.contains("45m"), "{}", tasks);
+}
+
+#[test]
+fn uncertain_estimates_give_an_end_of_day_range() {
+    let dir = TestDir::new("uncertainty");
+    dir.add_work_context();
+    for (name, uncertainty) in [("Report", "10"), ("Email", "5")] {
+        dir.run_ok(&[
+            "add-task",
+            "--name",
+            name,
+            "--context",
+            "Work",
+            "--duration",
+            "25",
+            "--uncertainty",
+            uncertainty,
+        ]);
+    }
+
+    let output = dir.run_ok(&["timeline"]);
+
+    // 10m and 5m either way put the pessimistic end 30m after the optimistic one.
+    assert!(
+        output.contains(
+            "Plan ends at 10:00, between 09:45 and 10:15, with 50m (±15m) of work and 10m of breaks."
+        ),
+        "{}",
+        output
+    );
+    assert!(dir
+        .run_ok(&["show-task", "Report"])
+        .contains("- Duration: 25m (±10m)"));
+}
+
+#[test]
+fn show_context_lists_days_in_week_order() {
+    let dir = TestDir::new("show-context");
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Gym",
+        "--days",
+        "Fri,Mon-Thu",
+        "--start",
+        "07:00",
+        "--end",
+        "08:00",
+    ]);
+
+    let output = dir.run_ok(&["show-context", "Gym"]);
+
+    assert!(
+        output.starts_with("Context - Gym\n- Days: Mon, Tue, Wed, Thu, Fri\n"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn overnight_context_rolls_blocks_into_the_next_day() {
+    let dir = TestDir::new("overnight");
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Night",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "22:00",
+        "--end",
+        "06:00",
+    ]);
+    dir.run_ok(&[
+        "add-task",
+        "--name",
+        "Build",
+        "--context",
+        "Night",
+        "--duration",
+        "200",
+    ]);
+
+    dir.run_ok(&["timeline", "--save"]);
+
+    let data: serde_yaml::Value = serde_yaml::from_str(&dir.data()).unwrap();
+    let blocks = data["saved_schedules"][0][1].as_sequence().unwrap();
+    let work: Vec<(&str, &str)> = blocks
+        .iter()
+        .filter(|block| block["name"] == "Task - Build")
+        .map(|block| {
+            (
+                block["start_date"].as_str().unwrap(),
+                block["start_time"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        work,
+        [
+            ("2024-06-03", "22:00"),
+            ("2024-06-03", "22:30"),
+            ("2024-06-03", "23:00"),
+            ("2024-06-03", "23:30"),
+            ("2024-06-04", "00:15"),
+            ("2024-06-04", "00:45"),
+            ("2024-06-04", "01:15"),
+            ("2024-06-04", "01:45"),
+        ]
+    );
+    // The long break straddling midnight ends on the next day.
+    let straddling = blocks
+        .iter()
+        .find(|block| block["start_time"] == "23:55")
+        .unwrap();
+    assert_eq!(straddling["end_date"], "2024-06-04");
+}
+
+#[test]
+fn explain_unscheduled_gives_each_task_a_reason() {
+    let dir = TestDir::new("explain");
+    dir.add_work_context();
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Weekend",
+        "--days",
+        "Sat-Sun",
+        "--start",
+        "09:00",
+        "--end",
+        "10:00",
+    ]);
+    for (name, context, duration, priority) in [
+        ("Report", "Work", "25", "9"),
+        ("Big", "Work", "100", "5"),
+        ("Late", "Work", "25", "1"),
+        ("Garden", "Weekend", "25", "1"),
+        ("Trip", "Work", "25", "1"),
+        ("Old", "Work", "25", "1"),
+    ] {
+        dir.run_ok(&[
+            "add-task",
+            "--name",
+            name,
+            "--context",
+            context,
+            "--duration",
+            duration,
+            "--priority",
+            priority,
+        ]);
+    }
+    dir.run_ok(&["snooze-task", "--name", "Trip", "--until", "2024-06-10"]);
+    dir.run_ok(&["archive-task", "--name", "Old"]);
+
+    let output = dir.run_ok(&["timeline", "--explain-unscheduled"]);
+
+    let report = output.split("Tasks:\n").nth(1).unwrap();
+    assert_eq!(
+        report,
+        "  Report (scheduled: 25m)\n  \
+         Big (deferred: 25m today, 1h 15m left for another day)\n  \
+         Late (overflow: no room left in Work)\n  \
+         Garden (inactive: no window in Weekend that day)\n  \
+         Trip (snoozed: until 2024-06-10)\n",
+        "{}",
+        output
+    );
+    assert!(!output.contains("Unscheduled:"), "{}", output);
+}
+
+#[test]
+fn transition_takes_hours_and_minutes() {
+    let dir = TestDir::new("transition");
+
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Work",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "09:00",
+        "--end",
+        "17:00",
+        "--transition",
+        "1h30m",
+    ]);
+
+    assert!(dir.data().contains("transition: 90m"), "{}", dir.data());
+    let output = dir.run_ok(&["show-context", "Work"]);
+    assert!(output.contains("- Transition Time: 1h 30m\n"), "{}", output);
+}
+
+#[test]
+fn locked_schedule_ignores_later_task_changes() {
+    let dir = TestDir::new("lock");
+    dir.add_work_context();
+    dir.run_ok(&["add-task", "--name", "Email", "--context", "Work"]);
+
+    assert_eq!(
+        dir.run_ok(&["lock-schedule", "--date", "2024-06-04"]),
+        "Locked the schedule for 2024-06-04\n"
+    );
+    let locked = dir.run_ok(&["timeline", "--date", "2024-06-04"]);
+    dir.run_ok(&[
+        "add-task",
+        "--name",
+        "Urgent",
+        "--context",
+        "Work",
+        "--priority",
+        "10",
+        "--duration",
+        "60",
+    ]);
+    dir.run_ok(&["archive-task", "--name", "Email"]);
+
+    assert_eq!(dir.run_ok(&["timeline", "--date", "2024-06-04"]), locked);
+    assert!(locked.contains("Task - Email"), "{}", locked);
+
+    dir.run_ok(&["unlock-schedule", "--date", "2024-06-04"]);
+    let replanned = dir.run_ok(&["timeline", "--date", "2024-06-04"]);
+    assert!(replanned.contains("Task - Urgent"), "{}", replanned);
+    assert!(!replanned.contains("Task - Email"), "{}", replanned);
+}
+
+#[test]
+fn daily_reset_brings_back_yesterdays_finished_habit() {
+    let dir = TestDir::new("reset");
+    dir.add_work_context();
+    dir.run_ok(&[
+        "add-task",
+        "--name",
+        "Exercise",
+        "--context",
+        "Work",
+        "--duration",
+        "30",
+        "--reset",
+        "daily",
+    ]);
+    // Finished yesterday, with only part of the time it usually takes.
+    let data = dir
+        .data()
+        .replace("done: false", "done: true")
+        .replace("\n  duration: 30m", "\n  duration: 5m")
+        .replace(
+            "created: 2024-06-03T12:00:00",
+            "created: 2024-06-02T12:00:00",
+        );
+    fs::write(dir.path().join("preempt_data.yaml"), data).unwrap();
+
+    let output = dir.run_ok(&["timeline"]);
+
+    // Back to 30m, which takes two blocks where 5m took one.
+    assert_eq!(output.matches("Task - Exercise").count(), 2, "{}", output);
+    let data = dir.data();
+    assert!(data.contains("done: false"), "{}", data);
+    assert!(data.contains("last_reset: 2024-06-03T00:00:00"), "{}", data);
+    // Today's reset has happened, so finishing it again would stick until tomorrow.
+    fs::write(
+        dir.path().join("preempt_data.yaml"),
+        data.replace("done: false", "done: true"),
+    )
+    .unwrap();
+    assert_eq!(dir.run_ok(&["timeline"]), "Nothing planned.\n");
+}
+
+#[test]
+fn until_done_stops_at_max_days_and_reports_leftovers() {
+    let dir = TestDir::new("max-days");
+    dir.add_work_context();
+    // Never active again, so nothing will schedule its task.
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Old",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "11:00",
+        "--end",
+        "12:00",
+        "--until",
+        "2024-01-01",
+    ]);
+    dir.run_ok(&[
+        "add-task",
+        "--name",
+        "Backlog",
+        "--context",
+        "Work",
+        "--duration",
+        "300",
+    ]);
+    dir.run_ok(&["add-task", "--name", "Stale", "--context", "Old"]);
+
+    let output = dir.run_ok(&["timeline", "--until-done", "--max-days", "3"]);
+
+    let headers: Vec<&str> = output
+        .lines()
+        .filter(|line| line.starts_with("# "))
+        .collect();
+    assert_eq!(
+        headers,
+        ["# Mon 2024-06-03", "# Tue 2024-06-04", "# Wed 2024-06-05"],
+        "{}",
+        output
+    );
+    assert!(
+        output.ends_with("Left after 3 days:\n  Backlog (2h 30m left)\n  Stale (25m left)\n"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn per_context_scope_allows_a_name_once_per_context() {
+    let dir = TestDir::new("unique-scope");
+    dir.add_work_context();
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Personal",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "18:00",
+        "--end",
+        "19:00",
+    ]);
+    dir.run_ok(&["add-task", "--name", "Email", "--context", "Work"]);
+    assert_eq!(
+        dir.run(&["add-task", "--name", "Email", "--context", "Personal"])
+            .status
+            .code(),
+        Some(1)
+    );
+
+    let data = dir
+        .data()
+        .replace("unique_scope: global", "unique_scope: per_context");
+    fs::write(dir.path().join("preempt_data.yaml"), data).unwrap();
+
+    dir.run_ok(&[
+        "add-task",
+        "--name",
+        "Email",
+        "--context",
+        "Personal",
+        "--duration",
+        "40",
+    ]);
+    let output = dir.run(&["add-task", "--name", "email", "--context", "Work"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(dir.run_ok(&["list-tasks"]).lines().count(), 2);
+    assert!(dir
+        .run_ok(&["show-task", "Email", "--context", "Personal"])
+        .contains("- Duration: 40m"));
+    assert_eq!(dir.run_ok(&["check"]), "No problems found\n");
+}
+
+#[test]
+fn context_picks_the_task_to_change_when_names_are_shared() {
+    let dir = TestDir::new("shared-names");
+    fs::create_dir_all(dir.path().join("config/preempt")).unwrap();
+    fs::write(
+        dir.path().join("config/preempt/config.toml"),
+        "unique_scope = \"per_context\"\n",
+    )
+    .unwrap();
+    dir.add_work_context();
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Personal",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "18:00",
+        "--end",
+        "19:00",
+    ]);
+    dir.run_ok(&["add-task", "--name", "Email", "--context", "Work"]);
+    dir.run_ok(&["add-task", "--name", "Email", "--context", "Personal"]);
+
+    dir.run_ok(&[
+        "snooze-task",
+        "--name",
+        "Email",
+        "--context",
+        "Personal",
+        "--until",
+        "2024-06-10",
+    ]);
+    dir.run_ok(&["remove-task", "--name", "Email", "--context", "Work"]);
+
+    assert!(dir.data().contains("unique_scope: per_context"));
+    assert_eq!(dir.run_ok(&["list-tasks"]).lines().count(), 1);
+    assert!(dir
+        .run_ok(&["show-task", "Email", "--context", "Personal"])
+        .contains("2024-06-10"));
+    assert_eq!(
+        dir.run(&["remove-task", "--name", "Email", "--context", "Work"])
+            .status
+            .code(),
+        Some(1)
+    );
+}
+
+#[test]
+fn tasks_sharing_a_name_are_credited_only_with_their_own_blocks() {
+    let dir = TestDir::new("shared-credit");
+    fs::create_dir_all(dir.path().join("config/preempt")).unwrap();
+    fs::write(
+        dir.path().join("config/preempt/config.toml"),
+        "unique_scope = \"per_context\"\n",
+    )
+    .unwrap();
+    dir.add_work_context();
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Personal",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "18:00",
+        "--end",
+        "18:30",
+    ]);
+    dir.run_ok(&["add-task", "--name", "Email", "--context", "Work"]);
+    dir.run_ok(&[
+        "add-task",
+        "--name",
+        "Email",
+        "--context",
+        "Personal",
+        "--duration",
+        "75",
+    ]);
+
+    let explained = dir.run_ok(&["timeline", "--explain-unscheduled"]);
+    assert!(
+        explained.ends_with(
+            "Tasks:\n  Email (scheduled: 25m)\n  Email (deferred: 25m today, 50m left for another day)\n"
+        ),
+        "{}",
+        explained
+    );
+
+    let plan = dir.run_ok(&["timeline", "--until-done"]);
+    assert!(plan.contains("# Wed 2024-06-05"), "{}", plan);
+    assert!(!plan.contains("# Thu"), "{}", plan);
+
+    // A scheduled Work Email doesn't hide the Personal one that got no room.
+    dir.run_ok(&[
+        "add-task",
+        "--name",
+        "Call",
+        "--context",
+        "Personal",
+        "--duration",
+        "30",
+        "--priority",
+        "9",
+    ]);
+    let explained = dir.run_ok(&["timeline", "--explain-unscheduled"]);
+    assert!(
+        explained.contains("  Email (overflow: no room left in Personal)\n"),
+        "{}",
+        explained
+    );
+}
+
+#[test]
+fn max_tasks_caps_distinct_tasks_per_day() {
+    let dir = TestDir::new("max-tasks");
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Work",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "09:00",
+        "--end",
+        "12:00",
+        "--max-tasks",
+        "2",
+    ]);
+    for name in ["Report", "Email", "Review"] {
+        dir.add_work_task(name, "25", "5");
+    }
+
+    let output = dir.run_ok(&["timeline"]);
+
+    assert_eq!(scheduled_tasks(&output), ["Report", "Email"], "{}", output);
+}
+
+#[test]
+fn bash_completion_covers_subcommands() {
+    let dir = TestDir::new("completion");
+
+    let output = dir.run_ok(&["completion", "--shell", "bash"]);
+
+    assert!(!output.is_empty());
+    assert!(output.contains("add-task"), "{}", output);
+    assert!(!dir.path().join("preempt_data.yaml").exists());
+}
+
+#[test]
+fn import_csv_reports_the_row_with_a_bad_priority() {
+    let dir = TestDir::new("import-csv");
+    dir.add_work_context();
+    let csv = dir.path().join("tasks.csv");
+    fs::write(
+        &csv,
+        "name,duration,priority,context,description\n\
+         \"Call Bob, then Ann\",30,4,Work,Phone calls, both of them\n\
+         Email,25,11,Work,\n",
+    )
+    .unwrap();
+
+    let output = dir.run_ok(&["import-csv", "--file", csv.to_str().unwrap()]);
+
+    assert!(output.contains("Imported 1 tasks"), "{}", output);
+    assert!(
+        output.contains("line 3: invalid priority '11'"),
+        "{}",
+        output
+    );
+    let task = dir.run_ok(&["show-task", "Call Bob, then Ann"]);
+    assert!(task.contains("Phone calls, both of them"), "{}", task);
+    assert_eq!(dir.run_ok(&["list-tasks"]).lines().count(), 1);
+}
+
+#[test]
+fn timeline_limit_shows_the_first_blocks_and_counts_the_rest() {
+    let dir = TestDir::new("limit");
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Work",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "09:00",
+        "--end",
+        "10:25",
+    ]);
+    dir.add_work_task("Report", "75", "1");
+    assert_eq!(dir.run_ok(&["timeline"]).matches(" | ").count(), 5);
+
+    let output = dir.run_ok(&["timeline", "--limit", "2"]);
+
+    assert_eq!(
+        output,
+        "== Work ==\n\
+         09:00:00 - 09:25:00 | Task - Report\n\
+         09:25:00 - 09:30:00 | Break (5 minutes)\n\
+         ... and 3 more\n\
+         Plan ends at 10:25 with 1h 15m of work and 10m of breaks.\n"
+    );
+}
+
+#[test]
+fn archived_task_is_listed_only_on_request() {
+    let dir = TestDir::new("archive");
+    dir.add_work_context();
+    dir.add_work_task("Email", "25", "1");
+    dir.add_work_task("Old report", "25", "1");
+
+    dir.run_ok(&["archive-task", "--name", "Old report"]);
+
+    let listed = dir.run_ok(&["list-tasks"]);
+    assert_eq!(listed.lines().count(), 1, "{}", listed);
+    assert!(!listed.contains("Old report"), "{}", listed);
+    let all = dir.run_ok(&["list-tasks", "--include-archived"]);
+    assert_eq!(all.lines().count(), 2, "{}", all);
+    assert!(all.contains("Old report"), "{}", all);
+}
+
+#[test]
+fn context_color_is_left_out_when_not_a_terminal() {
+    let dir = TestDir::new("color");
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Work",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "09:00",
+        "--end",
+        "10:00",
+        "--color",
+        "blue",
+    ]);
+    dir.add_work_task("Email", "25", "1");
+
+    assert!(dir.data().contains("color: blue"), "{}", dir.data());
+    let output = dir.run_ok(&["timeline"]);
+    assert!(output.starts_with("== Work ==\n"), "{:?}", output);
+    assert!(!dir.run_ok(&["list-contexts"]).contains('\x1b'));
+    assert!(!dir.run_ok(&["show-context", "Work"]).contains('\x1b'));
+}
+
+#[test]
+fn today_lists_tasks_in_active_contexts_and_due_today() {
+    let dir = TestDir::new("today");
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Standup",
+        "--days",
+        "Mon",
+        "--start",
+        "09:00",
+        "--end",
+        "09:30",
+    ]);
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Weekend",
+        "--days",
+        "Sat",
+        "--start",
+        "09:00",
+        "--end",
+        "10:00",
+    ]);
+    dir.run_ok(&["add-task", "--name", "Notes", "--context", "Standup"]);
+    dir.run_ok(&["add-task", "--name", "Garden", "--context", "Weekend"]);
+    dir.run_ok(&["add-task", "--name", "Taxes", "--deadline", "2024-06-03"]);
+    dir.run_ok(&["add-task", "--name", "Later", "--deadline", "2024-06-04"]);
+
+    let output = dir.run_ok(&["today"]);
+
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", output);
+    assert!(lines[0].starts_with("Notes "), "{}", output);
+    assert!(lines[1].starts_with("Taxes "), "{}", output);
+}
+
+#[test]
+fn shuffle_low_varies_by_date_and_repeats_within_one() {
+    let dir = TestDir::new("shuffle");
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Work",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "09:00",
+        "--end",
+        "12:00",
+    ]);
+    for chore in ["Dishes", "Laundry", "Vacuum", "Plants", "Mail"] {
+        dir.add_work_task(chore, "25", "1");
+    }
+    let order = |date: &str| {
+        scheduled_tasks(&dir.run_ok(&["timeline", "--shuffle-low", "--date", date]))
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<String>>()
+    };
+
+    let monday = order("2024-06-03");
+    let tuesday = order("2024-06-04");
+
+    assert_eq!(monday.len(), 5);
+    assert_ne!(monday, tuesday);
+    assert_eq!(order("2024-06-03"), monday);
+    assert_eq!(order("2024-06-04"), tuesday);
+}
+
+#[test]
+fn priority_boost_puts_an_old_task_ahead() {
+    let dir = TestDir::new("aging");
+    dir.add_work_context();
+    dir.add_work_task("Fresh", "25", "4");
+    dir.add_work_task("Old", "50", "4");
+    // Only the second task, Old, was created ten days ago.
+    let data = dir.data();
+    let (before, after) = data.rsplit_once("created: 2024-06-03T12:00:00").unwrap();
+    let data = format!("{}created: 2024-05-24T12:00:00{}", before, after);
+    fs::write(dir.path().join("preempt_data.yaml"), data).unwrap();
+
+    let unaged = dir.run_ok(&["timeline"]);
+    let aged = dir.run_ok(&["timeline", "--priority-boost", "7"]);
+
+    assert_eq!(scheduled_tasks(&unaged), ["Fresh", "Old"], "{}", unaged);
+    assert_eq!(scheduled_tasks(&aged), ["Old", "Old"], "{}", aged);
+}
+
+#[test]
+fn add_context_with_unparseable_days_fails() {
+    let dir = TestDir::new("bad-days");
+
+    let output = dir.run(&[
+        "add-context",
+        "--name",
+        "Work",
+        "--days",
+        "Funday",
+        "--start",
+        "09:00",
+        "--end",
+        "10:00",
+    ]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Unrecognized day 'Funday'"), "{}", stderr);
+    assert!(!dir.path().join("preempt_data.yaml").exists());
+}
+
+#[test]
+fn extended_day_fills_both_windows() {
+    let dir = TestDir::new("extend");
+    dir.add_work_context();
+    dir.add_work_task("Report", "100", "5");
+    dir.run_ok(&[
+        "add-exception",
+        "--context",
+        "Work",
+        "--date",
+        "2024-06-03",
+        "--start",
+        "14:00",
+        "--end",
+        "15:00",
+        "--mode",
+        "extend",
+    ]);
+
+    let output = dir.run_ok(&["timeline"]);
+
+    for start in ["09:00", "09:30", "14:00", "14:30"] {
+        assert!(
+            output.contains(&format!("{}:00 - ", start)),
+            "missing {}: {}",
+            start,
+            output
+        );
+    }
+    assert!(output.contains("with 1h 40m of work"), "{}", output);
+}
+
+#[test]
+fn show_context_date_shows_the_exception_window() {
+    let dir = TestDir::new("show-context-date");
+    dir.add_work_context();
+    dir.run_ok(&[
+        "add-exception",
+        "--context",
+        "Work",
+        "--date",
+        "2024-06-04",
+        "--start",
+        "13:00",
+        "--end",
+        "15:30",
+    ]);
+
+    let excepted = dir.run_ok(&["show-context", "Work", "--date", "2024-06-04"]);
+    let regular = dir.run_ok(&["show-context", "Work", "--date", "2024-06-05"]);
+    let weekend = dir.run_ok(&["show-context", "Work", "--date", "2024-06-08"]);
+
+    assert!(
+        excepted.ends_with("- On 2024-06-04: 13:00 to 15:30\n"),
+        "{}",
+        excepted
+    );
+    assert!(
+        regular.ends_with("- On 2024-06-05: 09:00 to 10:00\n"),
+        "{}",
+        regular
+    );
+    assert!(
+        weekend.ends_with("- On 2024-06-08: inactive\n"),
+        "{}",
+        weekend
+    );
+}
+
+#[test]
+fn focus_context_runs_tasks_without_breaks() {
+    let dir = TestDir::new("focus");
+    dir.add_work_context();
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Deep",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "14:00",
+        "--end",
+        "17:00",
+        "--focus",
+    ]);
+    dir.add_work_task("Email", "50", "1");
+    for (name, duration) in [("Design", "90"), ("Review", "40")] {
+        dir.run_ok(&[
+            "add-task",
+            "--name",
+            name,
+            "--context",
+            "Deep",
+            "--duration",
+            duration,
+        ]);
+    }
+
+    let output = dir.run_ok(&["timeline"]);
+
+    let (work, deep) = output.split_once("== Deep ==\n").unwrap();
+    assert!(work.contains("| Break"), "{}", output);
+    assert!(
+        deep.starts_with(
+            "14:00:00 - 15:30:00 | Task - Design\n\
+             15:30:00 - 16:10:00 | Task - Review\n"
+        ),
+        "{}",
+        output
+    );
+    assert!(!deep.contains("| Break"), "{}", output);
+}
+
+#[test]
+fn version_reports_the_package_version_and_data_file() {
+    let dir = TestDir::new("version");
+
+    let output = dir.run_ok(&["version"]);
+
+    assert!(
+        output.starts_with(&format!("preempt {}\n", env!("CARGO_PKG_VERSION"))),
+        "{}",
+        output
+    );
+    assert!(
+        output.contains(&dir.path().join("preempt_data.yaml").display().to_string()),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn weighted_strategy_puts_the_higher_priority_first() {
+    let dir = TestDir::new("weighted");
+    dir.add_work_context();
+    dir.add_work_task("Eight", "20", "8");
+    dir.add_work_task("Ten", "25", "10");
+
+    let multilevel = dir.run_ok(&["timeline"]);
+    let weighted = dir.run_ok(&["timeline", "--strategy", "weighted"]);
+
+    // Both are High under the priority classes, so the one added first goes first.
+    assert_eq!(
+        scheduled_tasks(&multilevel),
+        ["Eight", "Ten"],
+        "{}",
+        multilevel
+    );
+    assert_eq!(scheduled_tasks(&weighted), ["Ten", "Eight"], "{}", weighted);
+}
+
+#[test]
+fn from_stdin_adds_each_new_name_and_explains_skips() {
+    let dir = TestDir::new("from-stdin");
+    dir.add_work_context();
+
+    let output = dir.run_with_stdin(
+        &["add-task", "--from-stdin"],
+        "Email\n\n  Groceries \nemail\n",
+    );
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Added 2 tasks\nSkipped 'email': Task already exists\n"
+    );
+    let tasks = dir.run_ok(&["list-tasks"]);
+    assert_eq!(tasks.lines().count(), 2, "{}", tasks);
+    assert!(tasks.contains("Groceries"), "{}", tasks);
+}
+
+#[test]
+fn week_start_sunday_puts_sunday_first() {
+    let dir = TestDir::new("week-start");
+    dir.add_work_context();
+    let headers = |output: String| -> Vec<String> {
+        output
+            .lines()
+            .filter(|line| line.starts_with("# "))
+            .map(String::from)
+            .collect()
+    };
+
+    let monday_first = headers(dir.run_ok(&["timeline", "--week"]));
+    let sunday_first = headers(dir.run_ok(&["timeline", "--week", "--week-start", "Sun"]));
+
+    assert_eq!(monday_first.len(), 7);
+    assert_eq!(monday_first[0], "# Mon 2024-06-03");
+    assert_eq!(monday_first[6], "# Sun 2024-06-09");
+    assert_eq!(
+        sunday_first,
+        [
+            "# Sun 2024-06-02",
+            "# Mon 2024-06-03",
+            "# Tue 2024-06-04",
+            "# Wed 2024-06-05",
+            "# Thu 2024-06-06",
+            "# Fri 2024-06-07",
+            "# Sat 2024-06-08",
+        ]
+    );
+    let data = dir.data().replace("week_start: Mon", "week_start: Sun");
+    fs::write(dir.path().join("preempt_data.yaml"), data).unwrap();
+    assert_eq!(headers(dir.run_ok(&["timeline", "--week"])), sunday_first);
+}
+
+#[test]
+fn config_file_settings_are_applied() {
+    let dir = TestDir::new("config");
+    fs::create_dir_all(dir.path().join("config/preempt")).unwrap();
+    fs::write(
+        dir.path().join("config/preempt/config.toml"),
+        "timezone = \"+14:00\"\n\
+         \n\
+         [pomodoro]\n\
+         work_minutes = 50\n\
+         short_break_minutes = 10\n\
+         long_break_minutes = 20\n\
+         long_break_every = 4\n",
+    )
+    .unwrap();
+    dir.add_work_context();
+    dir.add_work_task("Report", "50", "1");
+
+    let output = dir.run_ok(&["timeline"]);
+
+    // Noon UTC is 02:00 the next day at +14:00.
+    assert!(
+        dir.data().contains("created: 2024-06-04T02:00:00"),
+        "{}",
+        dir.data()
+    );
+    assert_eq!(
+        output,
+        "== Work ==\n\
+         09:00:00 - 09:50:00 | Task - Report\n\
+         09:50:00 - 10:00:00 | Break (10 minutes)\n\
+         Plan ends at 10:00 with 50m of work and 10m of breaks.\n"
+    );
+}
+
+#[test]
+fn removing_a_context_reports_its_tasks_as_orphaned() {
+    let dir = TestDir::new("orphaned");
+    dir.add_work_context();
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Home",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "18:00",
+        "--end",
+        "19:00",
+    ]);
+    dir.add_work_task("Report", "25", "1");
+    dir.run_ok(&["add-task", "--name", "Dishes", "--context", "Home"]);
+
+    dir.run_ok(&["remove-context", "--name", "Work"]);
+    let output = dir.run_ok(&["timeline"]);
+
+    assert!(output.contains("| Task - Dishes\n"), "{}", output);
+    assert!(
+        output.ends_with("Unscheduled:\n  Report (orphaned: no such context)\n"),
+        "{}",
+        output
+    );
+    assert!(dir.run_ok(&["list-tasks"]).contains("Report"));
+}
+
+#[test]
+fn align_rounds_block_starts_to_five_minutes() {
+    let dir = TestDir::new("align");
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Work",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "09:02",
+        "--end",
+        "11:00",
+    ]);
+    dir.add_work_task("Report", "100", "8");
+    let start_minutes = |output: &str| -> Vec<u32> {
+        output
+            .lines()
+            .filter(|line| line.contains(" | "))
+            .map(|line| line[3..5].parse().unwrap())
+            .collect()
+    };
+
+    let unaligned = dir.run_ok(&["timeline"]);
+    let aligned = dir.run_ok(&["timeline", "--align", "5"]);
+
+    assert_eq!(start_minutes(&unaligned)[0], 2, "{}", unaligned);
+    let starts = start_minutes(&aligned);
+    assert_eq!(starts.len(), 7, "{}", aligned);
+    assert!(starts.iter().all(|minute| minute % 5 == 0), "{}", aligned);
+}
+
+#[test]
+fn show_schedule_prints_the_saved_timeline() {
+    let dir = TestDir::new("show-schedule");
+    dir.add_work_context();
+    dir.add_work_task("Report", "50", "1");
+
+    let timeline = dir.run_ok(&["timeline", "--save"]);
+    dir.add_work_task("Urgent", "60", "10");
+
+    assert_eq!(
+        dir.run_ok(&["show-schedule", "--date", "2024-06-03"]),
+        timeline.replace("Saved the schedule for 2024-06-03\n", "")
+    );
+}
+
+#[test]
+fn output_file_matches_what_stdout_shows() {
+    let dir = TestDir::new("output-file");
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Work",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "09:00",
+        "--end",
+        "10:00",
+        "--color",
+        "green",
+    ]);
+    dir.add_work_task("Report", "50", "1");
+    let path = dir.path().join("out/today.txt");
+
+    let printed = dir.run_ok(&["timeline"]);
+    let output = dir.run_ok(&["timeline", "--output-file", path.to_str().unwrap()]);
+
+    assert_eq!(
+        output,
+        format!("Wrote the timeline to '{}'\n", path.display())
+    );
+    let written = fs::read_to_string(&path).unwrap();
+    assert_eq!(written, printed);
+    assert!(!written.contains('\x1b'), "{:?}", written);
+}
+
+#[test]
+fn strict_timeline_fails_on_an_orphaned_task() {
+    let dir = TestDir::new("strict-orphan");
+    dir.add_work_context();
+    dir.add_work_task("Report", "25", "1");
+    dir.run_ok(&["remove-context", "--name", "Work"]);
+    assert!(dir.run(&["timeline"]).status.success());
+
+    let output = dir.run(&["--strict", "timeline"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Report"), "{}", stderr);
+    assert!(stderr.contains("orphaned"), "{}", stderr);
+}
+
+#[test]
+fn purge_needs_confirm_and_backs_up_first() {
+    let dir = TestDir::new("purge");
+    dir.add_work_context();
+    dir.add_work_task("Write report", "50", "5");
+
+    let refused = dir.run(&["purge"]);
+    assert_eq!(refused.status.code(), Some(1));
+    assert!(dir.data().contains("Write report"));
+
+    let output = dir.run_ok(&["purge", "--confirm"]);
+    assert!(output.contains("Purged all data"), "{}", output);
+    assert!(!dir.data().contains("Write report"), "{}", dir.data());
+    assert!(!dir.data().contains("Work"), "{}", dir.data());
+    let backup =
+        fs::read_to_string(dir.path().join("preempt_data.20240603T120000.bak.yaml")).unwrap();
+    assert!(backup.contains("Write report"), "{}", backup);
+}
+
+#[test]
+fn long_break_comes_after_every_third_block() {
+    let dir = TestDir::new("cadence");
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Work",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "09:00",
+        "--end",
+        "12:00",
+    ]);
+    dir.add_work_task("Long", "4h", "5");
+    dir.run_ok(&[
+        "save-preset",
+        "cadence",
+        "--long-break-every",
+        "3",
+        "--long-break-minutes",
+        "30",
+    ]);
+
+    let output = dir.run_ok(&["timeline", "--preset", "cadence"]);
+
+    let breaks: Vec<&str> = output
+        .lines()
+        .filter(|line| line.contains("| Break"))
+        .take(4)
+        .collect();
+    assert_eq!(
+        breaks,
+        [
+            "09:25:00 - 09:30:00 | Break (5 minutes)",
+            "09:55:00 - 10:00:00 | Break (5 minutes)",
+            "10:25:00 - 10:55:00 | Break (30 minutes)",
+            "11:20:00 - 11:25:00 | Break (5 minutes)",
+        ],
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn profiles_keep_separate_data() {
+    let dir = TestDir::new("profile");
+    dir.add_work_context();
+    dir.add_work_task("Default task", "25", "5");
+    dir.run_ok(&[
+        "--profile",
+        "work",
+        "add-context",
+        "--name",
+        "Work",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "09:00",
+        "--end",
+        "10:00",
+    ]);
+    dir.run_ok(&[
+        "--profile",
+        "work",
+        "add-task",
+        "--name",
+        "Profile task",
+        "--context",
+        "Work",
+        "--duration",
+        "25",
+    ]);
+
+    let default_tasks = dir.run_ok(&["list-tasks"]);
+    let work_tasks = dir.run_ok(&["--profile", "work", "list-tasks"]);
+
+    assert!(default_tasks.contains("Default task"), "{}", default_tasks);
+    assert!(!default_tasks.contains("Profile task"), "{}", default_tasks);
+    assert!(work_tasks.contains("Profile task"), "{}", work_tasks);
+    assert!(!work_tasks.contains("Default task"), "{}", work_tasks);
+    assert!(dir.path().join("preempt_work.yaml").exists());
+}
+
+#[test]
+fn import_holidays_cancels_each_date_and_keeps_existing_exceptions() {
+    let dir = TestDir::new("holidays");
+    dir.add_work_context();
+    dir.add_work_task("Report", "25", "5");
+    let holidays = dir.path().join("holidays.txt");
+    fs::write(&holidays, "2024-06-04\n2024-06-05\nsoon\n").unwrap();
+    let holidays = holidays.to_str().unwrap();
+
+    let output = dir.run_ok(&["import-holidays", "--context", "Work", "--file", holidays]);
+
+    assert_eq!(
+        output,
+        "Cancelled 'Work' on 2 days\n\
+         Skipped line 3: invalid date 'soon'\n"
+    );
+    for date in ["2024-06-04", "2024-06-05"] {
+        let timeline = dir.run_ok(&["timeline", "--date", date]);
+        assert!(scheduled_tasks(&timeline).is_empty(), "{}", timeline);
+    }
+
+    let output = dir.run_ok(&["import-holidays", "--context", "Work", "--file", holidays]);
+
+    assert_eq!(
+        output,
+        "Cancelled 'Work' on 0 days\n\
+         Kept the existing exception on 2024-06-04 (line 1)\n\
+         Kept the existing exception on 2024-06-05 (line 2)\n\
+         Skipped line 3: invalid date 'soon'\n"
+    );
+}
+
+#[test]
+fn weighted_low_gives_higher_low_priorities_more_turns() {
+    let dir = TestDir::new("weighted-low");
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Work",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "09:00",
+        "--end",
+        "11:00",
+    ]);
+    dir.add_work_task("Zero", "3h", "0");
+    dir.add_work_task("Two", "3h", "2");
+    let count = |timeline: &str, name: &str| {
+        scheduled_tasks(timeline)
+            .into_iter()
+            .filter(|task| *task == name)
+            .count()
+    };
+
+    let even = dir.run_ok(&["timeline"]);
+    let weighted = dir.run_ok(&["timeline", "--weighted-low"]);
+
+    assert_eq!(
+        (count(&even, "Two"), count(&even, "Zero")),
+        (2, 2),
+        "{}",
+        even
+    );
+    assert_eq!(
+        (count(&weighted, "Two"), count(&weighted, "Zero")),
+        (3, 1),
+        "{}",
+        weighted
+    );
+}
+
+#[test]
+fn presets_apply_their_saved_settings() {
+    let dir = TestDir::new("presets");
+    dir.add_work_context();
+    dir.add_work_task("Report", "50", "5");
+    dir.run_ok(&["save-preset", "focus", "--breaks-as", "free"]);
+    dir.run_ok(&["save-preset", "normal"]);
+
+    let focus = dir.run_ok(&["timeline", "--preset", "focus"]);
+    let normal = dir.run_ok(&["timeline", "--preset", "normal"]);
+
+    assert_eq!(scheduled_tasks(&focus), ["Report", "Report"], "{}", focus);
+    assert!(!focus.contains("| Break"), "{}", focus);
+    assert_eq!(scheduled_tasks(&normal), ["Report", "Report"], "{}", normal);
+    assert!(normal.contains("| Break"), "{}", normal);
+}
+
+#[test]
+fn pinned_task_keeps_its_time_whatever_its_priority() {
+    let dir = TestDir::new("pinned");
+    dir.add_work_context();
+    dir.add_work_task("Top", "50", "9");
+    dir.run_ok(&[
+        "add-task",
+        "--name",
+        "Standup",
+        "--context",
+        "Work",
+        "--duration",
+        "15",
+        "--priority",
+        "1",
+        "--at",
+        "09:30",
+    ]);
+
+    let output = dir.run_ok(&["timeline"]);
+
+    assert!(
+        output.contains("\n09:00:00 - 09:25:00 | Task - Top\n"),
+        "{}",
+        output
+    );
+    assert!(
+        output.contains("\n09:30:00 - 09:45:00 | Task - Standup\n"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn last_work_block_stops_at_the_end_of_the_window() {
+    let dir = TestDir::new("window-end");
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Work",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "09:00",
+        "--end",
+        "10:10",
+    ]);
+    dir.add_work_task("Report", "2h", "5");
+
+    let output = dir.run_ok(&["timeline"]);
+
+    assert_eq!(
+        output,
+        "== Work ==\n\
+         09:00:00 - 09:25:00 | Task - Report\n\
+         09:25:00 - 09:30:00 | Break (5 minutes)\n\
+         09:30:00 - 09:55:00 | Task - Report\n\
+         09:55:00 - 10:00:00 | Break (5 minutes)\n\
+         10:00:00 - 10:10:00 | Task - Report\n\
+         Plan ends at 10:10 with 1h of work and 10m of breaks.\n"
+    );
+}
+
+#[test]
+fn priority_seven_and_up_is_scheduled_as_high() {
+    let dir = TestDir::new("high-threshold");
+    dir.add_work_context();
+    dir.add_work_task("Five", "25", "5");
+    dir.add_work_task("Eight", "25", "8");
+
+    let output = dir.run_ok(&["timeline"]);
+
+    // Priority 8 used to fall through to Low and wait behind Medium work.
+    assert_eq!(scheduled_tasks(&output), ["Eight", "Five"], "{}", output);
+}
+
+#[test]
+fn explain_priority_shows_each_task_class() {
+    let dir = TestDir::new("explain-priority");
+    dir.add_work_context();
+    dir.add_work_task("Nine", "25", "9");
+    dir.add_work_task("Five", "25", "5");
+    dir.add_work_task("One", "25", "1");
+
+    let output = dir.run_ok(&["list-tasks", "--explain-priority"]);
+
+    let class = |name: &str| {
+        output
+            .lines()
+            .find(|line| line.starts_with(name))
+            .and_then(|line| line.rsplit("class ").next())
+            .unwrap_or_default()
+            .to_string()
+    };
+    assert_eq!(class("Nine"), "High", "{}", output);
+    assert_eq!(class("Five"), "Medium", "{}", output);
+    assert_eq!(class("One"), "Low", "{}", output);
+}
+
+#[cfg(unix)]
+#[test]
+fn read_only_data_file_explains_the_permission_error() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = TestDir::new("read-only");
+    dir.add_work_context();
+    let data = dir.path().join("preempt_data.yaml");
+    fs::set_permissions(&data, fs::Permissions::from_mode(0o444)).unwrap();
+    // Root can write to it anyway, so there's nothing to check.
+    if fs::OpenOptions::new().write(true).open(&data).is_ok() {
+        return;
+    }
+
+    let output = dir.run(&["add-task", "--name", "Report", "--context", "Work"]);
+
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Permission denied writing"), "{}", stderr);
+    assert!(stderr.contains("--data-dir"), "{}", stderr);
+    assert!(!dir.data().contains("Report"), "{}", dir.data());
+}
+
+#[test]
+fn data_file_falls_back_to_the_current_directory() {
+    let dir = TestDir::new("fallback-dir");
+    let fallback = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_preempt"))
+            .args(["--now", NOW])
+            .args(args)
+            .current_dir(dir.path())
+            .env("XDG_CONFIG_HOME", dir.path().join("config"))
+            .env_remove("HOME")
+            .env_remove("XDG_DATA_HOME")
+            .env_remove("PREEMPT_DATA_DIR")
+            .output()
+            .unwrap()
+    };
+    // The user database can still give a home directory, and then there's no fallback. Version
+    // doesn't touch the data, so it checks that without writing to the real data directory.
+    let warning = "couldn't find a data directory for this platform";
+    if !String::from_utf8_lossy(&fallback(&["version"]).stderr).contains(warning) {
+        return;
+    }
+
+    let output = fallback(&[
+        "add-context",
+        "--name",
+        "Work",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "09:00",
+        "--end",
+        "10:00",
+    ]);
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(warning), "{}", stderr);
+    assert!(stderr.contains("preempt_data.yaml"), "{}", stderr);
+    assert!(dir.data().contains("name: Work"), "{}", dir.data());
+    let listed = fallback(&["list-contexts"]);
+    assert!(
+        String::from_utf8_lossy(&listed.stdout).contains("Work"),
+        "{}",
+        String::from_utf8_lossy(&listed.stdout)
+    );
+}
+
+#[test]
+fn higher_priority_context_owns_the_overlap() {
+    let dir = TestDir::new("context-priority");
+    dir.add_work_context();
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Calls",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "09:30",
+        "--end",
+        "10:30",
+        "--priority",
+        "5",
+    ]);
+    dir.add_work_task("Report", "2h", "5");
+    dir.run_ok(&[
+        "add-task",
+        "--name",
+        "Call",
+        "--context",
+        "Calls",
+        "--duration",
+        "2h",
+        "--priority",
+        "5",
+    ]);
+
+    let output = dir.run_ok(&["timeline"]);
+
+    // Work was added first but only keeps the half hour Calls doesn't want.
+    let work = output.split("== Calls ==").next().unwrap();
+    assert_eq!(scheduled_tasks(work), ["Report"], "{}", output);
+    assert!(
+        output.contains("\n09:30:00 - 09:55:00 | Task - Call\n"),
+        "{}",
+        output
+    );
+    assert!(
+        output.contains("\n10:00:00 - 10:25:00 | Task - Call\n"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn list_tasks_since_leaves_out_older_tasks() {
+    let dir = TestDir::new("since");
+    dir.add_work_context();
+    // Added a week before the frozen clock.
+    let output = dir
+        .command_at(
+            "2024-05-27T12:00:00Z",
+            &["add-task", "--name", "Old", "--context", "Work"],
+        )
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    dir.add_work_task("New", "25", "5");
+
+    let output = dir.run_ok(&["list-tasks", "--since", "2024-06-01"]);
+
+    assert!(output.contains("New"), "{}", output);
+    assert!(!output.contains("Old"), "{}", output);
+    let all = dir.run_ok(&["list-tasks", "--since", "2024-05-27"]);
+    assert!(all.contains("Old") && all.contains("New"), "{}", all);
+}
+
+#[test]
+fn max_consecutive_interleaves_a_long_task_with_a_short_one() {
+    let dir = TestDir::new("max-consecutive");
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Work",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "09:00",
+        "--end",
+        "12:00",
+    ]);
+    dir.add_work_task("Long", "100", "8");
+    dir.add_work_task("Short", "50", "8");
+    let order = |args: &[&str]| {
+        let output = dir.run_ok(args);
+        scheduled_tasks(&output)
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<String>>()
+    };
+
+    assert_eq!(
+        order(&["timeline"]),
+        ["Long", "Long", "Long", "Long", "Short", "Short"]
+    );
+    assert_eq!(
+        order(&["timeline", "--max-consecutive", "1"]),
+        ["Long", "Short", "Long", "Short", "Long", "Long"]
+    );
+    // The weighted strategy puts the shorter task first, but it yields just the same.
+    assert_eq!(
+        order(&["timeline", "--strategy", "weighted"]),
+        ["Short", "Short", "Long", "Long", "Long", "Long"]
+    );
+    assert_eq!(
+        order(&[
+            "timeline",
+            "--strategy",
+            "weighted",
+            "--max-consecutive",
+            "1"
+        ]),
+        ["Short", "Long", "Short", "Long", "Long", "Long"]
+    );
+}
+
+#[test]
+fn contexts_differing_only_by_case_are_reported_and_can_be_fixed() {
+    let dir = TestDir::new("case-duplicates");
+    dir.add_work_context();
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Home",
+        "--days",
+        "Sat",
+        "--start",
+        "09:00",
+        "--end",
+        "10:00",
+    ]);
+    // A hand edit renames Home so it clashes with Work.
+    let data = dir.data().replace("name: Home", "name: WORK");
+    fs::write(dir.path().join("preempt_data.yaml"), data).unwrap();
+
+    let check = dir.run(&["check"]);
+
+    assert_eq!(check.status.code(), Some(2));
+    let output = String::from_utf8_lossy(&check.stdout);
+    assert!(
+        output.contains("'Work'") && output.contains("'WORK'"),
+        "{}",
+        output
+    );
+
+    // The bad file can still be worked with, so it can be repaired.
+    dir.run_ok(&["list-contexts"]);
+    dir.run_ok(&["remove-context", "--name", "work"]);
+    dir.add_work_context();
+    assert_eq!(dir.run_ok(&["check"]), "No problems found\n");
+}
+
+#[test]
+fn timeline_context_only_plans_that_context() {
+    let dir = TestDir::new("context-subset");
+    dir.add_work_context();
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Home",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "18:00",
+        "--end",
+        "19:00",
+    ]);
+    dir.add_work_task("Report", "25", "5");
+    dir.run_ok(&[
+        "add-task",
+        "--name",
+        "Dishes",
+        "--context",
+        "Home",
+        "--duration",
+        "25",
+    ]);
+
+    let output = dir.run_ok(&["timeline", "--context", "Home"]);
+
+    assert!(output.contains("== Home =="), "{}", output);
+    assert!(!output.contains("== Work =="), "{}", output);
+    assert_eq!(scheduled_tasks(&output), ["Dishes"], "{}", output);
+}
+
+#[test]
+fn check_exits_non_zero_only_when_it_finds_problems() {
+    let dir = TestDir::new("check");
+    dir.add_work_context();
+    dir.add_work_task("Report", "25", "5");
+
+    assert_eq!(dir.run_ok(&["check"]), "No problems found\n");
+
+    dir.run_ok(&["remove-context", "--name", "Work"]);
+    let output = dir.run(&["check"]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "- Task 'Report' refers to the missing context 'Work'\n"
+    );
+}
+
+#[test]
+fn task_names_are_stored_with_tidy_spacing() {
+    let dir = TestDir::new("name-spacing");
+    dir.add_work_context();
+
+    let output = dir.run_ok(&[
+        "add-task",
+        "--name",
+        "  Write   report  ",
+        "--context",
+        "Work",
+    ]);
+
+    assert_eq!(output, "Added task 'Write report'\n");
+    assert!(
+        dir.data().contains("name: Write report\n"),
+        "{}",
+        dir.data()
+    );
+    let shown = dir.run_ok(&["show-task", " write  REPORT"]);
+    assert!(shown.contains("Write report"), "{}", shown);
+    let duplicate = dir.run(&["add-task", "--name", "Write report ", "--context", "Work"]);
+    assert_eq!(duplicate.status.code(), Some(1));
+}
+
+#[test]
+fn add_task_with_a_bad_priority_exits_non_zero() {
+    let dir = TestDir::new("bad-priority");
+    dir.add_work_context();
+
+    for priority in ["high", "11"] {
+        let output = dir.run(&[
+            "add-task",
+            "--name",
+            "Report",
+            "--context",
+            "Work",
+            "--priority",
+            priority,
+        ]);
+
+        assert_eq!(output.status.code(), Some(1), "{}", priority);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("--priority"), "{}", stderr);
+    }
+    assert!(!dir.data().contains("Report"), "{}", dir.data());
+}
+
+#[test]
+fn show_free_marks_a_context_without_tasks() {
+    let dir = TestDir::new("show-free");
+    dir.add_work_context();
+
+    let output = dir.run_ok(&["timeline", "--show-free"]);
+
+    assert_eq!(
+        output,
+        "== Work ==\n\
+         09:00:00 - 10:00:00 | Free: Work\n\
+         Nothing planned.\n"
+    );
+    assert!(!dir.run_ok(&["timeline"]).contains("Free"));
+}
+
+#[test]
+fn min_block_defers_a_task_when_too_little_time_is_left() {
+    // Ten minutes are left after Report and its break.
+    let plan = |name: &str, min_block: &[&str]| {
+        let dir = TestDir::new(name);
+        dir.run_ok(&[
+            "add-context",
+            "--name",
+            "Work",
+            "--days",
+            "Mon-Fri",
+            "--start",
+            "09:00",
+            "--end",
+            "09:40",
+        ]);
+        dir.add_work_task("Report", "25", "5");
+        let mut args = vec![
+            "add-task",
+            "--name",
+            "Call",
+            "--context",
+            "Work",
+            "--duration",
+            "25",
+            "--priority",
+            "4",
+        ];
+        args.extend_from_slice(min_block);
+        dir.run_ok(&args);
+        dir.run_ok(&["timeline"])
+    };
+
+    let output = plan("min-block-unset", &[]);
+    assert_eq!(scheduled_tasks(&output), ["Report", "Call"], "{}", output);
+
+    let output = plan("min-block", &["--min-block", "20"]);
+    assert_eq!(scheduled_tasks(&output), ["Report"], "{}", output);
+    assert!(
+        output.contains("Call (overflow: no room left in Work)"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn plan_adds_the_task_and_shows_the_timeline() {
+    let dir = TestDir::new("plan");
+    dir.add_work_context();
+
+    let output = dir.run_ok(&[
+        "plan",
+        "--name",
+        "Report",
+        "--context",
+        "Work",
+        "--duration",
+        "25",
+    ]);
+
+    assert_eq!(
+        output,
+        "Added task 'Report'\n\
+         == Work ==\n\
+         09:00:00 - 09:25:00 | Task - Report\n\
+         09:25:00 - 09:30:00 | Break (5 minutes)\n\
+         Plan ends at 09:30 with 25m of work and 5m of breaks.\n"
+    );
+    assert!(dir.data().contains("name: Report"), "{}", dir.data());
+}
+
+#[test]
+fn list_contexts_counts_each_contexts_open_tasks() {
+    let dir = TestDir::new("list-contexts");
+    dir.add_work_context();
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Home",
+        "--days",
+        "Sat,Sun",
+        "--start",
+        "18:00",
+        "--end",
+        "20:00",
+    ]);
+    dir.add_work_task("Report", "25", "5");
+    dir.add_work_task("Email", "25", "5");
+    dir.run_ok(&[
+        "add-task",
+        "--name",
+        "Dishes",
+        "--context",
+        "Home",
+        "--duration",
+        "25",
+    ]);
+
+    let output = dir.run_ok(&["list-contexts"]);
+
+    assert_eq!(
+        output,
+        "Work | Mon, Tue, Wed, Thu, Fri | 09:00 to 10:00 | 2 open tasks\n\
+         Home | Sat, Sun | 18:00 to 20:00 | 1 open task\n"
+    );
+}
+
+#[test]
+fn only_a_missed_hard_deadline_fails_strict() {
+    let add_big = |dir: &TestDir, kind: &str| {
+        dir.add_work_context();
+        dir.run_ok(&[
+            "add-task",
+            "--name",
+            "Big",
+            "--context",
+            "Work",
+            "--duration",
+            "3h",
+            "--deadline",
+            "2024-06-03",
+            "--deadline-kind",
+            kind,
+        ]);
+    };
+
+    let soft = TestDir::new("soft-deadline");
+    add_big(&soft, "soft");
+    let output = soft.run_ok(&["--strict", "timeline"]);
+    assert!(
+        output.contains("Warning: 'Big' won't be finished by its soft deadline of 2024-06-03"),
+        "{}",
+        output
+    );
+
+    let hard = TestDir::new("hard-deadline");
+    add_big(&hard, "hard");
+    let output = hard.run(&["--strict", "timeline"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Error: 'Big' won't be finished by its hard deadline of 2024-06-03"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn legacy_seconds_load_and_are_rewritten_readably() {
+    let dir = TestDir::new("readable");
+    dir.add_work_context();
+    dir.add_work_task("Report", "50", "5");
+    let data = dir.data();
+    assert!(data.contains("duration: 50m"), "{}", data);
+    assert!(data.contains("start: 09:00\n"), "{}", data);
+    // The format files had before durations and times were stored readably.
+    let legacy = data
+        .replace("duration: 50m", "duration: 3000")
+        .replace("start: 09:00\n", "start: 09:00:00\n")
+        .replace("transition: 0m", "transition: 300");
+    fs::write(dir.path().join("preempt_data.yaml"), legacy).unwrap();
+
+    let output = dir.run_ok(&["list-tasks"]);
+
+    assert!(output.contains("50m"), "{}", output);
+    let data = dir.data();
+    assert!(data.contains("duration: 50m"), "{}", data);
+    assert!(data.contains("start: 09:00\n"), "{}", data);
+    assert!(data.contains("transition: 5m"), "{}", data);
+}
+
+#[test]
+fn add_task_records_the_frozen_clock_as_created() {
+    let dir = TestDir::new("created");
+    dir.add_work_context();
+
+    let output = dir
+        .command_at(
+            "2024-06-05T08:30:00Z",
+            &["add-task", "--name", "Report", "--context", "Work"],
+        )
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert!(
+        dir.data().contains("created: 2024-06-05T08:30:00"),
+        "{}",
+        dir.data()
+    );
+}
+
+#[test]
+fn someday_tasks_are_listed_but_not_scheduled() {
+    let dir = TestDir::new("someday");
+    dir.add_work_context();
+    dir.add_work_task("Report", "25", "5");
+    dir.add_work_task("Someday", "25", "0");
+    let data = dir.data().replace(
+        "schedule_zero_priority: true",
+        "schedule_zero_priority: false",
+    );
+    fs::write(dir.path().join("preempt_data.yaml"), data).unwrap();
+
+    let timeline = dir.run_ok(&["timeline"]);
+    let tasks = dir.run_ok(&["list-tasks"]);
+
+    assert_eq!(scheduled_tasks(&timeline), ["Report"], "{}", timeline);
+    assert!(tasks.contains("Someday"), "{}", tasks);
+    assert!(dir.data().contains("schedule_zero_priority: false"));
+}
+
+#[test]
+fn task_falls_back_to_its_second_context() {
+    let dir = TestDir::new("fallback");
+    dir.add_work_context();
+    dir.run_ok(&[
+        "add-context",
+        "--name",
+        "Evening",
+        "--days",
+        "Mon-Fri",
+        "--start",
+        "18:00",
+        "--end",
+        "19:00",
+    ]);
+    // Work is full with the first task, so the second moves to Evening.
+    dir.add_work_task("Report", "50", "8");
+    dir.run_ok(&[
+        "add-task",
+        "--name",
+        "Email",
+        "--context",
+        "Work",
+        "--context",
+        "Evening",
+        "--duration",
+        "25",
+        "--priority",
+        "5",
+    ]);
+
+    let output = dir.run_ok(&["timeline"]);
+
+    let (work, evening) = output.split_once("== Evening ==").unwrap();
+    assert_eq!(scheduled_tasks(work), ["Report", "Report"], "{}", output);
+    assert_eq!(scheduled_tasks(evening), ["Email"], "{}", output);
+}
+
+#[test]
+fn task_uses_its_second_context_when_the_first_is_off_that_day() {
+    let dir = TestDir::new("fallback-inactive");
+    for (name, days, start, end) in [
+        ("Weekend", "Sat-Sun", "10:00", "12:00"),
+        ("Evening", "Mon-Fri", "18:00", "19:00"),
+    ] {
+        dir.run_ok(&[
+            "add-context",
+            "--name",
+            name,
+            "--days",
+            days,
+            "--start",
+            start,
+            "--end",
+            end,
+        ]);
+    }
+    dir.run_ok(&[
+        "add-task",
+        "--name",
+        "Garden",
+        "--context",
+        "Weekend",
+        "--context",
+        "Evening",
+    ]);
+
+    // The frozen clock is on a Monday, when Weekend isn't active.
+    let output = dir.run_ok(&["timeline"]);
+
+    assert!(!output.contains("== Weekend =="), "{}", output);
+    let (_, evening) = output.split_once("== Evening ==").unwrap();
+    assert_eq!(scheduled_tasks(evening), ["Garden"], "{}", output);
+}
+
+#[test]
+fn week_numbers_prefix_each_day_header() {
+    let dir = TestDir::new("week-numbers");
+    dir.add_work_context();
+
+    let output = dir.run_ok(&["timeline", "--week", "--week-numbers"]);
+
+    let headers: Vec<&str> = output
+        .lines()
+        .filter(|line| line.starts_with("# "))
+        .collect();
+    assert_eq!(headers.len(), 7, "{}", output);
+    assert_eq!(headers[0], "# W23 Mon 2024-06-03");
+    assert_eq!(headers[6], "# W23 Sun 2024-06-09");
+}
+
+#[test]
+fn explicit_order_goes_ahead_of_the_added_order() {
+    let dir = TestDir::new("order");
+    dir.add_work_context();
+    dir.add_work_task("First", "25", "5");
+    dir.add_work_task("Second", "25", "5");
+
+    let output = dir.run_ok(&["timeline"]);
+    assert_eq!(scheduled_tasks(&output), ["First", "Second"], "{}", output);
+
+    dir.run_ok(&["reorder-task", "--name", "Second", "--order", "1"]);
+    let output = dir.run_ok(&["timeline"]);
+    assert_eq!(scheduled_tasks(&output), ["Second", "First"], "{}", output);
+}
+
+#[test]
+fn snoozed_task_comes_back_on_its_date() {
+    let dir = TestDir::new("snooze");
+    dir.add_work_context();
+    dir.add_work_task("Report", "25", "5");
+    dir.add_work_task("Later", "25", "5");
+
+    dir.run_ok(&["snooze-task", "--name", "Later", "--until", "2024-06-04"]);
+
+    let today = dir.run_ok(&["timeline"]);
+    let tomorrow = dir.run_ok(&["timeline", "--date", "2024-06-04"]);
+    assert_eq!(scheduled_tasks(&today), ["Report"], "{}", today);
+    assert_eq!(
+        scheduled_tasks(&tomorrow),
+        ["Report", "Later"],
+        "{}",
+        tomorrow
+    );
+}
@@ -0,0 +1,238 @@
+//! Contexts built directly through the library.
+
+use chrono::{Duration, NaiveDate, NaiveTime, Weekday};
+use preempt::context::{set_colors, Context, ContextException, ExceptionMode};
+
+fn time(hour: u32, minute: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+/// A context open 09:00 to 17:00 on the given days.
+fn context(name: &str, days: &[Weekday]) -> Context {
+    Context::new(
+        name,
+        days.to_vec(),
+        time(9, 0),
+        time(17, 0),
+        Duration::zero(),
+    )
+}
+
+#[test]
+fn new_context_drops_repeated_days() {
+    let nine = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+    let five = NaiveTime::from_hms_opt(17, 0, 0).unwrap();
+
+    let mut context = Context::new(
+        "Work",
+        vec![Weekday::Tue, Weekday::Mon, Weekday::Mon, Weekday::Tue],
+        nine,
+        five,
+        Duration::zero(),
+    );
+
+    assert_eq!(context.days, [Weekday::Mon, Weekday::Tue]);
+    assert!(context.render().contains("- Days: Mon, Tue\n"));
+
+    context.set_days(vec![Weekday::Fri, Weekday::Wed, Weekday::Fri]);
+    assert_eq!(context.days, [Weekday::Wed, Weekday::Fri]);
+}
+
+#[test]
+fn set_color_rejects_unknown_colors_and_stores_hex() {
+    let mut context = context("Work", &[Weekday::Mon]);
+
+    assert!(context.set_color("mauve").is_err());
+    assert!(context.set_color("#12345").is_err());
+    assert_eq!(context.color(), None);
+
+    context.set_color("#1E90FF").unwrap();
+    assert_eq!(context.color(), Some("#1E90FF"));
+    set_colors(true);
+    assert_eq!(context.colorize("Work"), "\x1b[38;2;30;144;255mWork\x1b[0m");
+}
+
+#[test]
+fn active_date_range_bounds_the_timeblock() {
+    let mut internship = context("Internship", &[Weekday::Mon]);
+    internship.active_from = Some(date(2024, 6, 3));
+    internship.active_until = Some(date(2024, 8, 26));
+
+    assert!(internship.get_timeblock(date(2024, 5, 27)).is_empty());
+    assert!(!internship.is_active_at(date(2024, 5, 27)));
+    let inside = internship.get_timeblock(date(2024, 7, 1));
+    assert_eq!(inside.len(), 1);
+    assert_eq!(
+        (inside[0].start_time, inside[0].end_time),
+        (time(9, 0), time(17, 0))
+    );
+    assert!(internship.is_active_at(date(2024, 8, 26)));
+    assert!(internship.get_timeblock(date(2024, 9, 2)).is_empty());
+    assert!(!internship.is_active_at(date(2024, 9, 2)));
+}
+
+#[test]
+fn try_new_rejects_a_context_without_days() {
+    let result = Context::try_new("Work", vec![], time(9, 0), time(17, 0), Duration::zero());
+
+    assert_eq!(result.err(), Some("A context needs at least one day"));
+}
+
+/// A context open between the given (hour, minute) times on the given days.
+fn window(days: &[Weekday], start: (u32, u32), end: (u32, u32)) -> Context {
+    Context::new(
+        "Window",
+        days.to_vec(),
+        time(start.0, start.1),
+        time(end.0, end.1),
+        Duration::zero(),
+    )
+}
+
+#[test]
+fn contexts_sharing_hours_on_a_day_overlap() {
+    let morning = window(&[Weekday::Mon, Weekday::Tue], (9, 0), (12, 0));
+    let late_morning = window(&[Weekday::Tue], (11, 0), (13, 0));
+
+    assert!(morning.overlaps(&late_morning));
+    assert!(late_morning.overlaps(&morning));
+}
+
+#[test]
+fn adjacent_contexts_do_not_overlap() {
+    let morning = window(&[Weekday::Mon], (9, 0), (12, 0));
+    let afternoon = window(&[Weekday::Mon], (12, 0), (17, 0));
+
+    assert!(!morning.overlaps(&afternoon));
+    assert!(!afternoon.overlaps(&morning));
+}
+
+#[test]
+fn disjoint_contexts_do_not_overlap() {
+    let monday = window(&[Weekday::Mon], (9, 0), (12, 0));
+    let tuesday = window(&[Weekday::Tue], (9, 0), (12, 0));
+    let monday_evening = window(&[Weekday::Mon], (18, 0), (20, 0));
+
+    assert!(!monday.overlaps(&tuesday));
+    assert!(!monday.overlaps(&monday_evening));
+}
+
+#[test]
+fn overnight_context_overlaps_into_the_next_morning() {
+    let night = window(&[Weekday::Mon], (22, 0), (6, 0));
+
+    assert!(night.overlaps(&window(&[Weekday::Mon], (23, 0), (23, 30))));
+    assert!(night.overlaps(&window(&[Weekday::Tue], (5, 0), (7, 0))));
+    assert!(!night.overlaps(&window(&[Weekday::Mon], (5, 0), (7, 0))));
+    assert!(!night.overlaps(&window(&[Weekday::Tue], (6, 0), (7, 0))));
+}
+
+#[test]
+fn exception_hours_can_overlap_another_context() {
+    let monday = window(&[Weekday::Mon], (9, 0), (12, 0));
+    let mut tuesday = window(&[Weekday::Tue], (9, 0), (12, 0));
+    assert!(!monday.overlaps(&tuesday));
+
+    // Moved to Monday 2024-06-03 for one week, during Monday's hours.
+    tuesday.add_exception(ContextException::new(
+        date(2024, 6, 3),
+        time(11, 0),
+        time(13, 0),
+        Duration::zero(),
+    ));
+
+    assert!(monday.overlaps(&tuesday));
+    assert!(tuesday.overlaps(&monday));
+}
+
+#[test]
+fn weekly_active_minutes_sum_the_active_days() {
+    let mut three_days = context("Work", &[Weekday::Mon, Weekday::Wed, Weekday::Fri]);
+    let monday = date(2024, 6, 3);
+    assert_eq!(three_days.weekly_active_minutes(monday), 3 * 8 * 60);
+
+    // Wednesday ends at noon this week.
+    three_days.add_exception(ContextException::new(
+        date(2024, 6, 5),
+        time(9, 0),
+        time(12, 0),
+        Duration::zero(),
+    ));
+
+    assert_eq!(
+        three_days.weekly_active_minutes(monday),
+        2 * 8 * 60 + 3 * 60
+    );
+}
+
+/// The day's windows as start and end times.
+fn windows_on(context: &Context, day: NaiveDate) -> Vec<(NaiveTime, NaiveTime)> {
+    context
+        .get_timeblock(day)
+        .iter()
+        .map(|block| (block.start_time, block.end_time))
+        .collect()
+}
+
+#[test]
+fn override_exception_replaces_the_regular_hours() {
+    let mut work = context("Work", &[Weekday::Mon]);
+    let exception =
+        ContextException::new(date(2024, 6, 3), time(13, 0), time(15, 0), Duration::zero());
+    assert_eq!(exception.mode(), ExceptionMode::Override);
+    work.add_exception(exception);
+
+    assert_eq!(
+        windows_on(&work, date(2024, 6, 3)),
+        [(time(13, 0), time(15, 0))]
+    );
+    assert_eq!(
+        windows_on(&work, date(2024, 6, 10)),
+        [(time(9, 0), time(17, 0))]
+    );
+}
+
+#[test]
+fn cancel_exception_leaves_no_window() {
+    let mut work = context("Work", &[Weekday::Mon]);
+    let exception = ContextException::cancellation(date(2024, 6, 3));
+    assert_eq!(exception.mode(), ExceptionMode::Cancel);
+    work.add_exception(exception);
+
+    assert!(work.get_timeblock(date(2024, 6, 3)).is_empty());
+    assert_eq!(windows_on(&work, date(2024, 6, 10)).len(), 1);
+}
+
+#[test]
+fn extend_exception_merges_with_overlapping_hours() {
+    let mut work = context("Work", &[Weekday::Mon]);
+    let exception =
+        ContextException::extension(date(2024, 6, 3), time(16, 0), time(19, 0), Duration::zero());
+    assert_eq!(exception.mode(), ExceptionMode::Extend);
+    work.add_exception(exception);
+
+    assert_eq!(
+        windows_on(&work, date(2024, 6, 3)),
+        [(time(9, 0), time(19, 0))]
+    );
+}
+
+#[test]
+fn extend_exception_apart_from_the_regular_hours_adds_a_window() {
+    let mut work = context("Work", &[Weekday::Mon]);
+    work.add_exception(ContextException::extension(
+        date(2024, 6, 3),
+        time(19, 0),
+        time(21, 0),
+        Duration::zero(),
+    ));
+
+    assert_eq!(
+        windows_on(&work, date(2024, 6, 3)),
+        [(time(9, 0), time(17, 0)), (time(19, 0), time(21, 0))]
+    );
+}
@@ -0,0 +1,322 @@
+//! The app model built directly through the library.
+
+use chrono::{Duration, NaiveDate, NaiveTime, Weekday};
+use preempt::context::Context;
+use preempt::model::{load, save, DataLocation, DataLock, PreemptApp, UniqueScope};
+use preempt::schedule::{worked_on, BreakMode, EnergyCurve, ScheduleOptions};
+use preempt::task::{Difficulty, Task};
+use preempt::timeblock::TimeBlock;
+
+fn task(name: &str, minutes: i64, priority: i32) -> Task {
+    Task::new_with_duration(
+        name.to_string(),
+        format!("About {}", name),
+        priority,
+        false,
+        Duration::minutes(minutes),
+        Some("Work".to_string()),
+    )
+}
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+/// An app with a Work context open 09:00 to 10:00 on weekdays.
+fn work_app() -> PreemptApp {
+    let mut app = PreemptApp::new();
+    app.add_context(Context::new(
+        "Work",
+        vec![
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+        ],
+        NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+        NaiveTime::from_hms_opt(10, 0, 0).unwrap(),
+        Duration::zero(),
+    ))
+    .unwrap();
+    app
+}
+
+#[test]
+fn add_task_returns_the_stored_task() {
+    let mut app = PreemptApp::new();
+
+    let added = app.add_task(task("Email", 40, 6)).unwrap();
+
+    assert_eq!(added.name, "Email");
+    assert_eq!(added.priority, 6);
+    assert_eq!(added.duration(), Duration::minutes(40));
+    assert_eq!(added.context(), Some("Work"));
+    let added = added as *const Task;
+    assert!(std::ptr::eq(added, app.get_task("Email", None).unwrap()));
+}
+
+#[test]
+fn add_task_rejects_a_duplicate() {
+    let mut app = PreemptApp::new();
+    app.add_task(task("Email", 40, 6)).unwrap();
+
+    assert_eq!(
+        app.add_task(task("email", 25, 1)).err(),
+        Some("Task already exists")
+    );
+    assert_eq!(app.list_tasks(false).len(), 1);
+    assert_eq!(
+        app.get_task("Email", None).unwrap().duration(),
+        Duration::minutes(40)
+    );
+}
+
+#[test]
+fn reschedule_incomplete_carries_the_unfinished_half_to_the_target_day() {
+    let mut app = work_app();
+    app.add_task(task("Report", 100, 1)).unwrap();
+    let (monday, tuesday, wednesday) = (date(2024, 6, 3), date(2024, 6, 4), date(2024, 6, 5));
+
+    let carried = app.reschedule_incomplete(monday, wednesday).unwrap();
+
+    assert_eq!(carried, ["Report"]);
+    let report = app.get_task("Report", None).unwrap();
+    assert_eq!(report.duration(), Duration::minutes(50));
+    assert!(!report.is_done());
+    let options = ScheduleOptions::default();
+    assert!(worked_on(&app.build_schedule_for(tuesday, &options), report).is_zero());
+    assert_eq!(
+        worked_on(&app.build_schedule_for(wednesday, &options), report),
+        Duration::minutes(50)
+    );
+}
+
+#[test]
+fn zero_duration_task_takes_no_time() {
+    let mut app = work_app();
+    app.add_task(task("Nothing", 0, 10)).unwrap();
+    app.add_task(task("Email", 25, 1)).unwrap();
+
+    let schedule = app.build_schedule_for(date(2024, 6, 3), &ScheduleOptions::default());
+
+    assert!(worked_on(&schedule, app.get_task("Nothing", None).unwrap()).is_zero());
+    assert_eq!(schedule[0].name.as_deref(), Some("Task - Email"));
+    assert_eq!(
+        schedule[0].start_time,
+        NaiveTime::from_hms_opt(9, 0, 0).unwrap()
+    );
+}
+
+#[test]
+fn dependency_tree_indents_each_level() {
+    let mut app = PreemptApp::new();
+    let mut ship = task("Ship", 25, 1);
+    ship.add_dependency("Test");
+    ship.add_dependency("Docs");
+    let mut test = task("Test", 25, 1);
+    test.add_dependency("Build");
+    for task in [ship, test, task("Build", 25, 1), task("Docs", 25, 1)] {
+        app.add_task(task).unwrap();
+    }
+
+    assert_eq!(
+        app.dependency_tree("ship").unwrap(),
+        ["Ship", "  Test", "    Build", "  Docs"]
+    );
+}
+
+#[test]
+fn dependency_tree_marks_a_cycle() {
+    let mut app = PreemptApp::new();
+    let mut first = task("First", 25, 1);
+    first.add_dependency("Second");
+    let mut second = task("Second", 25, 1);
+    second.add_dependency("First");
+    app.add_task(first).unwrap();
+    app.add_task(second).unwrap();
+
+    assert_eq!(
+        app.dependency_tree("First").unwrap(),
+        ["First", "  Second", "    First (cycle)"]
+    );
+}
+
+#[test]
+fn saved_schedule_is_recalled_unchanged() {
+    let mut app = work_app();
+    app.add_task(task("Report", 50, 1)).unwrap();
+    let monday = date(2024, 6, 3);
+    let schedule = app.build_schedule_for(monday, &ScheduleOptions::default());
+
+    app.save_schedule(monday, schedule.clone());
+    app.add_task(task("Urgent", 60, 10)).unwrap();
+
+    assert_eq!(app.saved_schedule(monday), Some(schedule.as_slice()));
+    assert_eq!(app.saved_schedule(date(2024, 6, 4)), None);
+}
+
+#[test]
+fn energy_peak_gets_the_highest_priority_task() {
+    let mut app = PreemptApp::new();
+    app.add_context(Context::new(
+        "Work",
+        vec![Weekday::Mon],
+        NaiveTime::from_hms_opt(9, 30, 0).unwrap(),
+        NaiveTime::from_hms_opt(11, 0, 0).unwrap(),
+        Duration::zero(),
+    ))
+    .unwrap();
+    app.add_task(task("Top", 25, 9)).unwrap();
+    app.add_task(task("Chore", 25, 1)).unwrap();
+    let monday = date(2024, 6, 3);
+    let at = |schedule: &[TimeBlock], hour: u32, minute: u32| {
+        schedule
+            .iter()
+            .find(|block| block.start_time == NaiveTime::from_hms_opt(hour, minute, 0).unwrap())
+            .and_then(|block| block.name.clone())
+    };
+
+    let flat = app.build_schedule_for(monday, &ScheduleOptions::default());
+    app.energy_curve = EnergyCurve([(9, 0.1), (10, 1.0)].into_iter().collect());
+    let curved = app.build_schedule_for(monday, &ScheduleOptions::default());
+
+    assert_eq!(at(&flat, 9, 30).as_deref(), Some("Task - Top"));
+    assert_eq!(at(&curved, 9, 30).as_deref(), Some("Task - Chore"));
+    // A short break, not a long one, even though 09:55 is a low energy time.
+    assert_eq!(at(&curved, 9, 55).as_deref(), Some("Break (5 minutes)"));
+    assert_eq!(at(&curved, 10, 0).as_deref(), Some("Task - Top"));
+}
+
+#[test]
+fn free_breaks_leave_gaps_between_task_blocks() {
+    let mut app = work_app();
+    app.add_task(task("Report", 50, 5)).unwrap();
+    let options = ScheduleOptions {
+        breaks: BreakMode::Free,
+        ..ScheduleOptions::default()
+    };
+
+    let schedule = app.build_schedule_for(date(2024, 6, 3), &options);
+
+    let names: Vec<_> = schedule.iter().map(|block| block.name.as_deref()).collect();
+    assert_eq!(names, [Some("Task - Report"), Some("Task - Report")]);
+    // The five minute rest is a gap rather than a block.
+    assert_eq!(
+        schedule[0].end_time,
+        NaiveTime::from_hms_opt(9, 25, 0).unwrap()
+    );
+    assert_eq!(
+        schedule[1].start_time,
+        NaiveTime::from_hms_opt(9, 30, 0).unwrap()
+    );
+}
+
+#[test]
+fn save_refuses_new_problems_but_not_known_ones() {
+    let dir = std::env::temp_dir().join(format!("preempt-model-save-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let location = DataLocation::new(Some(dir.clone()), None);
+    let mut app = work_app();
+    app.unique_scope = UniqueScope::PerContext;
+    app.add_task(task("Email", 25, 5)).unwrap();
+    let mut personal = task("Email", 25, 5);
+    personal.set_contexts(vec!["Personal".to_string()]);
+    app.add_task(personal).unwrap();
+    // Now two tasks share a name across the whole app.
+    app.unique_scope = UniqueScope::Global;
+    let problems = app.validate().unwrap_err();
+
+    let refused = save(&app, &location, &[]);
+
+    assert!(refused.unwrap_err().contains("Refusing to save"));
+    assert!(!location.path().exists());
+
+    save(&app, &location, &problems).unwrap();
+
+    assert!(location.path().exists());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn data_lock_is_held_until_dropped() {
+    let dir = std::env::temp_dir().join(format!("preempt-model-lock-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let location = DataLocation::new(Some(dir.clone()), None);
+
+    let lock = DataLock::acquire(&location).unwrap();
+    let second = DataLock::acquire(&location);
+
+    let message = second.err().unwrap();
+    assert!(
+        message.starts_with("Another preempt instance is running"),
+        "{}",
+        message
+    );
+    drop(lock);
+    assert!(DataLock::acquire(&location).is_ok());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn busiest_day_is_the_one_with_the_most_work() {
+    let mut app = work_app();
+    app.add_context(Context::new(
+        "Midweek",
+        vec![Weekday::Wed],
+        NaiveTime::from_hms_opt(13, 0, 0).unwrap(),
+        NaiveTime::from_hms_opt(15, 0, 0).unwrap(),
+        Duration::zero(),
+    ))
+    .unwrap();
+    app.add_task(task("Report", 120, 5)).unwrap();
+    let mut review = task("Review", 120, 5);
+    review.set_contexts(vec!["Midweek".to_string()]);
+    app.add_task(review).unwrap();
+    let monday = date(2024, 6, 3);
+
+    let (day, work) = app.busiest_day(monday);
+
+    // Wednesday has Midweek's four blocks on top of Work's two.
+    assert_eq!(day, date(2024, 6, 5));
+    assert_eq!(work, Duration::minutes(150));
+    assert_eq!(app.work_on(monday), Duration::minutes(50));
+}
+
+#[test]
+fn high_difficulty_task_takes_the_peak_hour() {
+    let mut app = work_app();
+    app.add_task(task("Easy", 25, 5)).unwrap();
+    let mut hard = task("Hard", 25, 5);
+    hard.set_difficulty(Some(Difficulty::High));
+    app.add_task(hard).unwrap();
+    let monday = date(2024, 6, 3);
+    let first = |schedule: Vec<TimeBlock>| schedule[0].name.clone().unwrap();
+
+    let flat = app.build_schedule_for(monday, &ScheduleOptions::default());
+    app.energy_curve = EnergyCurve([(9, 1.0), (13, 0.1)].into_iter().collect());
+    let curved = app.build_schedule_for(monday, &ScheduleOptions::default());
+
+    assert_eq!(first(flat), "Task - Easy");
+    assert_eq!(first(curved), "Task - Hard");
+}
+
+#[test]
+fn data_round_trips_through_a_plain_directory() {
+    // The same kind of location the current directory fallback uses, given explicitly.
+    let dir = std::env::temp_dir().join(format!("preempt-model-fallback-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let location = DataLocation::new(Some(dir.clone()), None);
+    let mut app = work_app();
+    app.add_task(task("Report", 50, 5)).unwrap();
+
+    save(&app, &location, &[]).unwrap();
+    let loaded = load(&location).unwrap();
+
+    assert!(!location.is_fallback());
+    assert_eq!(location.path(), dir.join("preempt_data.yaml"));
+    let report = loaded.get_task("Report", None).unwrap();
+    assert_eq!(report.duration(), Duration::minutes(50));
+    assert!(loaded.get_context("Work").is_some());
+    let _ = std::fs::remove_dir_all(&dir);
+}
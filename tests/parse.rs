@@ -0,0 +1,55 @@
+//! Command line parsing through the library, without running any commands.
+
+use chrono::{Duration, Weekday};
+use preempt::cli::{build_cli, parse_days, parse_duration};
+use preempt::task::{format_duration_in, DurationUnits};
+
+#[test]
+fn build_cli_parses_add_task_name() {
+    let matches = build_cli().get_matches_from(["preempt", "add-task", "--name", "x"]);
+
+    let (name, sub_m) = matches.subcommand().unwrap();
+    assert_eq!(name, "add-task");
+    assert_eq!(sub_m.value_of("name"), Some("x"));
+}
+
+#[test]
+fn parse_duration_counts_pomodoros() {
+    let pomodoro = Duration::minutes(25);
+
+    assert_eq!(parse_duration("3p", pomodoro), Ok(Duration::minutes(75)));
+    assert_eq!(parse_duration("1h30m", pomodoro), Ok(Duration::minutes(90)));
+    assert!(parse_duration("3x", pomodoro).is_err());
+}
+
+#[test]
+fn pomodoro_display_rounds_up_to_the_next_half() {
+    let units = DurationUnits::Pomodoros(Duration::minutes(25));
+
+    assert_eq!(
+        format_duration_in(Duration::minutes(75), units),
+        "3 pomodoros"
+    );
+    assert_eq!(
+        format_duration_in(Duration::minutes(25), units),
+        "1 pomodoro"
+    );
+    assert_eq!(
+        format_duration_in(Duration::minutes(30), units),
+        "1.5 pomodoros"
+    );
+    assert_eq!(
+        format_duration_in(Duration::minutes(40), units),
+        "2 pomodoros"
+    );
+}
+
+#[test]
+fn parse_days_reads_ranges_and_lists() {
+    use Weekday::*;
+
+    assert_eq!(parse_days("Mon-Fri"), Ok(vec![Mon, Tue, Wed, Thu, Fri]));
+    assert_eq!(parse_days("Fri-Mon"), Ok(vec![Fri, Sat, Sun, Mon]));
+    assert_eq!(parse_days("Mon,Wed-Thu,Sat"), Ok(vec![Mon, Wed, Thu, Sat]));
+    assert!(parse_days("Mon-Mon").is_err());
+}
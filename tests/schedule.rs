@@ -0,0 +1,153 @@
+//! Schedules built and post-processed through the library.
+
+use chrono::{Duration, NaiveDate, NaiveTime};
+use preempt::schedule::{coalesce, diff, render_break_ratio, summarize, to_compact, to_markdown};
+use preempt::timeblock::TimeBlock;
+
+fn time(hour: u32, minute: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+/// A Monday.
+fn monday() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 6, 3).unwrap()
+}
+
+/// A block on Monday, from and to (hour, minute).
+fn block(name: &str, start: (u32, u32), end: (u32, u32)) -> TimeBlock {
+    TimeBlock::new_named(
+        name.to_string(),
+        time(start.0, start.1),
+        time(end.0, end.1),
+        monday(),
+        monday(),
+    )
+}
+
+#[test]
+fn coalesce_merges_back_to_back_blocks_of_one_task() {
+    let blocks = vec![
+        block("Task - Report", (9, 0), (9, 25)),
+        block("Task - Report", (9, 25), (9, 50)),
+        block("Task - Report", (9, 50), (10, 15)),
+        block("Break (5 minutes)", (10, 15), (10, 20)),
+        block("Task - Report", (10, 20), (10, 45)),
+    ];
+
+    assert_eq!(
+        coalesce(blocks),
+        [
+            block("Task - Report", (9, 0), (10, 15)),
+            block("Break (5 minutes)", (10, 15), (10, 20)),
+            block("Task - Report", (10, 20), (10, 45)),
+        ]
+    );
+}
+
+#[test]
+fn summary_totals_are_the_sums_of_the_blocks() {
+    let blocks = vec![
+        block("Task - Report", (9, 0), (9, 25)),
+        block("Break (5 minutes)", (9, 25), (9, 30)),
+        block("Task - Email", (9, 30), (9, 40)),
+        block("Break (20 minutes)", (9, 40), (10, 0)),
+        block("Task - Report", (10, 0), (10, 25)),
+    ];
+    let total = |prefix: &str| {
+        blocks
+            .iter()
+            .filter(|block| block.name.as_deref().unwrap().starts_with(prefix))
+            .fold(Duration::zero(), |total, block| total + block.duration())
+    };
+
+    let summary = summarize(&blocks);
+
+    assert_eq!(summary.work, total("Task - "));
+    assert_eq!(summary.work, Duration::minutes(60));
+    assert_eq!(summary.breaks, total("Break"));
+    assert_eq!(summary.breaks, Duration::minutes(25));
+    assert_eq!(summary.ends_at, Some(time(10, 25)));
+}
+
+#[test]
+fn markdown_checks_off_tasks_and_lists_breaks() {
+    let blocks = vec![
+        block("Task - Report", (9, 0), (9, 25)),
+        block("Break (5 minutes)", (9, 25), (9, 30)),
+    ];
+
+    assert_eq!(
+        to_markdown(&blocks),
+        "## 2024-06-03\n\n\
+         - [ ] 09:00–09:25 Task - Report\n\
+         - 09:25–09:30 Break (5 minutes)\n"
+    );
+}
+
+#[test]
+fn compact_escapes_pipes_in_names() {
+    let blocks = vec![
+        block("Task - Read a|b", (9, 0), (9, 25)),
+        block("Task - C:\\temp", (9, 30), (9, 55)),
+    ];
+
+    assert_eq!(
+        to_compact(&blocks),
+        "09:00|09:25|Task - Read a\\|b\n09:30|09:55|Task - C:\\\\temp\n"
+    );
+}
+
+#[test]
+fn diff_reports_a_block_moved_half_an_hour() {
+    let old = vec![
+        block("Task - Email", (9, 0), (9, 25)),
+        block("Task - Report", (9, 30), (9, 55)),
+    ];
+    let new = vec![
+        block("Task - Email", (9, 0), (9, 25)),
+        block("Task - Report", (10, 0), (10, 25)),
+        block("Task - Review", (10, 30), (10, 55)),
+    ];
+
+    let changes = diff(&old, &new);
+
+    assert_eq!(changes.moved.len(), 1);
+    assert_eq!(changes.moved[0].old, old[1]);
+    assert_eq!(changes.moved[0].new, new[1]);
+    assert_eq!(changes.moved[0].shift, Duration::minutes(30));
+    assert_eq!(changes.added, [new[2].clone()]);
+    assert!(changes.removed.is_empty());
+}
+
+#[test]
+fn block_contains_its_start_but_not_its_end() {
+    let report = block("Task - Report", (9, 0), (9, 25));
+    let at = |hour, minute| monday().and_time(time(hour, minute));
+
+    assert!(report.contains(at(9, 0)));
+    assert!(report.contains(at(9, 10)));
+    assert!(!report.contains(at(9, 25)));
+    assert!(!report.contains(at(8, 59)));
+    assert!(!report.contains(monday().succ_opt().unwrap().and_time(time(9, 10))));
+}
+
+#[test]
+fn break_ratio_is_a_rounded_percentage_of_work() {
+    // 75 minutes of work and 15 of breaks.
+    let blocks = vec![
+        block("Task - Report", (9, 0), (9, 25)),
+        block("Break (5 minutes)", (9, 25), (9, 30)),
+        block("Task - Report", (9, 30), (9, 55)),
+        block("Break (10 minutes)", (9, 55), (10, 5)),
+        block("Task - Email", (10, 5), (10, 30)),
+    ];
+
+    let summary = summarize(&blocks);
+
+    assert_eq!(summary.break_percent(), Some(20));
+    assert_eq!(
+        render_break_ratio(&summary),
+        "Work 1h 15m / Break 15m (20% break)"
+    );
+    assert_eq!(summarize(&[]).break_percent(), None);
+}
@@ -0,0 +1,123 @@
+//! Tasks built directly through the library.
+
+use chrono::{Duration, NaiveDate, NaiveTime, Weekday};
+use preempt::context::Context;
+use preempt::task::{fit_column, format_duration, humanize_since, Task};
+
+/// An open task in the Work context.
+fn task(name: &str, minutes: i64, priority: i32) -> Task {
+    Task::new_with_duration(
+        name.to_string(),
+        format!("About {}", name),
+        priority,
+        false,
+        Duration::minutes(minutes),
+        Some("Work".to_string()),
+    )
+}
+
+#[test]
+fn split_divides_the_duration_evenly() {
+    let pieces = task("Write report", 90, 7).split(3);
+
+    let names: Vec<&str> = pieces.iter().map(|piece| piece.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "Write report (1/3)",
+            "Write report (2/3)",
+            "Write report (3/3)"
+        ]
+    );
+    for piece in &pieces {
+        assert_eq!(piece.duration(), Duration::minutes(30));
+        assert_eq!(piece.priority, 7);
+        assert_eq!(piece.context(), Some("Work"));
+    }
+}
+
+fn work_context() -> Context {
+    Context::new(
+        "Work",
+        vec![Weekday::Mon],
+        NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+        NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+        Duration::zero(),
+    )
+}
+
+#[test]
+fn context_match_finds_a_listed_context() {
+    let report = task("Report", 25, 1);
+
+    assert!(report.is_context_match("Work"));
+    assert!(!report.is_context_match("Home"));
+}
+
+#[test]
+fn context_match_ignores_case() {
+    let report = task("Report", 25, 1);
+
+    assert!(report.is_context_match("work"));
+    assert!(report.is_context_match("WORK"));
+}
+
+#[test]
+fn done_tasks_match_but_are_not_scheduled() {
+    let mut report = task("Report", 25, 1);
+    report.complete();
+
+    assert!(report.is_context_match("Work"));
+    assert!(!report.is_schedulable());
+    let open =
+        Task::filter_context_tasks(&work_context(), vec![report, task("Email", 25, 1)], |_| {
+            false
+        });
+    let names: Vec<&str> = open.iter().map(|task| task.name.as_str()).collect();
+    assert_eq!(names, ["Email"]);
+}
+
+#[test]
+fn task_without_a_context_matches_none() {
+    let chore = Task::new("Chore".to_string(), String::new(), 1, false, None);
+
+    assert!(!chore.is_context_match("Work"));
+    assert!(!chore.is_context_match(""));
+    assert!(chore.is_schedulable());
+}
+
+#[test]
+fn humanize_since_uses_the_largest_whole_unit() {
+    let now = NaiveDate::from_ymd_opt(2024, 6, 3)
+        .unwrap()
+        .and_hms_opt(12, 0, 0)
+        .unwrap();
+
+    assert_eq!(
+        humanize_since(now - Duration::minutes(5), now),
+        "5 minutes ago"
+    );
+    assert_eq!(humanize_since(now - Duration::hours(2), now), "2 hours ago");
+    assert_eq!(humanize_since(now - Duration::days(3), now), "3 days ago");
+    assert_eq!(humanize_since(now - Duration::seconds(30), now), "just now");
+}
+
+#[test]
+fn fit_column_pads_or_cuts_to_the_width() {
+    let long = "Write the quarterly report for the board";
+    assert_eq!(long.len(), 40);
+
+    assert_eq!(fit_column(long, 20), "Write the quarterly…");
+    assert_eq!(fit_column("Email", 8), "Email   ");
+    // Emoji are two columns wide, and a joined sequence is never split.
+    assert_eq!(fit_column("🎉🎉🎉 party", 5), "🎉🎉…");
+    assert_eq!(fit_column("👨‍👩‍👧 family", 4), "👨‍👩‍👧 …");
+}
+
+#[test]
+fn format_duration_shows_hours_and_minutes() {
+    assert_eq!(format_duration(Duration::minutes(90)), "1h 30m");
+    assert_eq!(format_duration(Duration::minutes(45)), "45m");
+    assert_eq!(format_duration(Duration::minutes(120)), "2h");
+    assert_eq!(format_duration(Duration::zero()), "0m");
+}
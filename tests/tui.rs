@@ -0,0 +1,122 @@
+//! The timeline view driven by keys, without a terminal.
+
+use chrono::{NaiveDate, NaiveTime};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use preempt::timeblock::TimeBlock;
+use preempt::tui::{Key, TimelineView};
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::widgets::Widget;
+
+fn block(name: &str, start: u32, end: u32) -> TimeBlock {
+    let date = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+    TimeBlock::new_named(
+        name.to_string(),
+        NaiveTime::from_hms_opt(start, 0, 0).unwrap(),
+        NaiveTime::from_hms_opt(end, 0, 0).unwrap(),
+        date,
+        date,
+    )
+}
+
+#[test]
+fn view_highlights_the_current_block_and_scrolls() {
+    let blocks = vec![
+        block("Email", 8, 9),
+        block("Report", 9, 10),
+        block("Lunch", 10, 11),
+        block("Review", 11, 12),
+    ];
+    let now = NaiveDate::from_ymd_opt(2024, 6, 3)
+        .unwrap()
+        .and_hms_opt(9, 30, 0)
+        .unwrap();
+
+    let mut view = TimelineView::new(blocks, now, 2);
+    assert_eq!(view.current(), Some(1));
+    assert_eq!(view.offset(), 1);
+    assert_eq!(
+        view.render(),
+        "> 09:00 - 10:00 | Report\n  10:00 - 11:00 | Lunch\nBlocks 2-3 of 4  [up/down] scroll  [q] quit\n"
+    );
+
+    assert!(view.handle(Key::Down));
+    assert!(view.handle(Key::Down));
+    assert_eq!(view.offset(), 2);
+    assert!(!view.render().contains('>'));
+
+    assert!(view.handle(Key::Up));
+    assert_eq!(view.offset(), 1);
+    assert!(!view.handle(Key::from_event(KeyEvent::new(
+        KeyCode::Char('q'),
+        KeyModifiers::NONE
+    ))));
+}
+
+#[test]
+fn widget_draws_the_visible_blocks_in_a_frame() {
+    let blocks = vec![block("Email", 8, 9), block("Report", 9, 10)];
+    let now = NaiveDate::from_ymd_opt(2024, 6, 3)
+        .unwrap()
+        .and_hms_opt(8, 15, 0)
+        .unwrap();
+    let view = TimelineView::new(blocks, now, 1);
+    let area = Rect::new(0, 0, 48, 3);
+    let mut buf = Buffer::empty(area);
+
+    Widget::render(&view, area, &mut buf);
+
+    let rows: Vec<String> = (0..area.height)
+        .map(|y| {
+            (0..area.width)
+                .map(|x| buf[(x, y)].symbol())
+                .collect::<String>()
+        })
+        .collect();
+    assert!(rows[0].contains("Today"), "{:?}", rows);
+    assert!(rows[1].contains("> 08:00 - 09:00 | Email"), "{:?}", rows);
+    assert!(rows[2].contains("Blocks 1-1 of 2"), "{:?}", rows);
+}
+
+#[test]
+fn terminal_keys_map_to_view_keys() {
+    let press = |code| Key::from_event(KeyEvent::new(code, KeyModifiers::NONE));
+
+    assert_eq!(press(KeyCode::Up), Key::Up);
+    assert_eq!(press(KeyCode::Char('j')), Key::Down);
+    assert_eq!(press(KeyCode::Esc), Key::Quit);
+    assert_eq!(press(KeyCode::Char('c')), Key::Other);
+    assert_eq!(
+        Key::from_event(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+        Key::Quit
+    );
+}
+
+#[test]
+fn resizing_keeps_every_block_reachable() {
+    let blocks = vec![
+        block("Email", 8, 9),
+        block("Report", 9, 10),
+        block("Lunch", 10, 11),
+        block("Review", 11, 12),
+    ];
+    let now = NaiveDate::from_ymd_opt(2024, 6, 3)
+        .unwrap()
+        .and_hms_opt(11, 30, 0)
+        .unwrap();
+
+    // Tall enough for everything, so the view starts at the top.
+    let mut view = TimelineView::new(blocks, now, 10);
+    assert_eq!(view.offset(), 0);
+
+    view.set_height(2);
+    view.handle(Key::Down);
+    view.handle(Key::Down);
+    view.handle(Key::Down);
+    assert_eq!(view.offset(), 2);
+    assert!(view.render().contains("> 11:00 - 12:00 | Review"));
+
+    // Growing again scrolls back rather than leaving the bottom blank.
+    view.set_height(3);
+    assert_eq!(view.offset(), 1);
+}